@@ -0,0 +1,190 @@
+use std::collections::{HashMap, HashSet};
+use atom::Atom;
+use error::Error;
+use module::{module_name, ImportSet};
+
+// Checks a program before it is evaluated for anything that would modify a
+// binding after it is made: any use of set!, or a define or import of a name
+// already bound in the same scope. `bound` holds the names already bound in
+// the scope the program will be evaluated in, and `exports` what each module
+// defined so far exports. Imports of modules defined neither there nor in the
+// program can't be checked until they are loaded.
+pub fn check_pure(program: &[Atom], bound: HashSet<String>, exports: HashMap<Vec<String>, Vec<String>>)
+    -> Result<(), Error> {
+    let mut bound = bound;
+    Checker { exports }.check_body(program, &mut bound)
+}
+
+struct Checker {
+    exports: HashMap<Vec<String>, Vec<String>>
+}
+
+impl Checker {
+    fn check_body(&mut self, body: &[Atom], bound: &mut HashSet<String>) -> Result<(), Error> {
+        for atom in body {
+            self.check_atom(atom, bound)?;
+        }
+        Ok(())
+    }
+
+    // Checks a body evaluated in a new scope in which `names` are bound.
+    fn check_scope<'a, I>(&mut self, names: I, body: &[Atom]) -> Result<(), Error>
+        where I: IntoIterator<Item = &'a str> {
+        let mut bound = names.into_iter().map(str::to_string).collect();
+        self.check_body(body, &mut bound)
+    }
+
+    fn check_atom(&mut self, atom: &Atom, bound: &mut HashSet<String>) -> Result<(), Error> {
+        let atoms = match *atom {
+            Atom::List(ref atoms) | Atom::Form(_, ref atoms) => atoms,
+            _ => return Ok(())
+        };
+        let (form, cdr) = match atoms.split_first() {
+            Some((Atom::Identifier(form), cdr)) => (form.as_str(), cdr),
+            _ => return self.check_body(atoms, bound)
+        };
+        match form {
+            "quote" => Ok(()),
+            "import" => {
+                // Import sets that are invalid fail when they are evaluated.
+                for set in cdr.iter().filter_map(|set| ImportSet::parse(set).ok()) {
+                    let names = match self.exports.get(set.module()).map(|exports| set.names(exports)) {
+                        Some(Ok(names)) => names,
+                        _ => continue
+                    };
+                    for (name, _) in names {
+                        bind(bound, name)?;
+                    }
+                }
+                Ok(())
+            },
+            // A module's body is evaluated in a scope of its own.
+            "define-module" => {
+                if let [name, Atom::List(export), ..] = cdr {
+                    if let Ok(name) = module_name(name) {
+                        let exports = export.iter().skip(1).filter_map(|name| match *name {
+                            Atom::Identifier(ref name) => Some(name.clone()),
+                            _ => None
+                        }).collect();
+                        self.exports.insert(name, exports);
+                    }
+                }
+                self.check_scope(None, cdr.get(2..).unwrap_or(&[]))
+            },
+            "set!" => {
+                Err(Error::new("set! is not allowed in pure mode", cdr.iter().take(1).cloned().collect()))
+            },
+            "define" => match cdr {
+                [Atom::Identifier(name), value] => {
+                    self.check_atom(value, bound)?;
+                    bind(bound, name.clone())
+                },
+                _ => self.check_body(cdr, bound)
+            },
+            "lambda" => match cdr.split_first() {
+                Some((params, body)) => {
+                    self.check_scope(parameter_names(params), &parameter_defaults(params))?;
+                    self.check_scope(parameter_names(params), body)
+                },
+                None => Ok(())
+            },
+            "let" | "let*" | "letrec" | "letrec*" => {
+                // A named let binds its name outside the scope of its body.
+                let cdr = match cdr.split_first() {
+                    Some((Atom::Identifier(_), cdr)) => cdr,
+                    _ => cdr
+                };
+                let (bindings, body) = match cdr.split_first() {
+                    Some((Atom::List(bindings), body)) => (bindings, body),
+                    _ => return self.check_body(cdr, bound)
+                };
+                let names = binding_names(bindings);
+                let inits: Vec<Atom> = bindings.iter().filter_map(binding_init).collect();
+                if form == "let" {
+                    self.check_body(&inits, bound)?;
+                } else {
+                    self.check_scope(names.iter().cloned(), &inits)?;
+                }
+                self.check_scope(names, body)
+            },
+            "do" => match cdr.split_first() {
+                Some((Atom::List(specs), rest)) => {
+                    let inits: Vec<Atom> = specs.iter().filter_map(binding_init).collect();
+                    self.check_body(&inits, bound)?;
+                    let steps: Vec<Atom> = specs.iter().filter_map(do_step).collect();
+                    let names = binding_names(specs);
+                    self.check_scope(names.iter().cloned(), &steps)?;
+                    self.check_scope(names, rest)
+                },
+                _ => self.check_body(cdr, bound)
+            },
+            "guard" => match cdr.split_first() {
+                Some((Atom::List(spec), body)) => {
+                    self.check_body(body, bound)?;
+                    match spec.split_first() {
+                        Some((Atom::Identifier(var), clauses)) => self.check_scope(Some(var.as_str()), clauses),
+                        _ => self.check_body(spec, bound)
+                    }
+                },
+                _ => self.check_body(cdr, bound)
+            },
+            _ => self.check_body(atoms, bound)
+        }
+    }
+}
+
+// Adds a name defined or imported to those bound in a scope, unless it is
+// already there.
+fn bind(bound: &mut HashSet<String>, name: String) -> Result<(), Error> {
+    if bound.contains(&name) {
+        return Err(Error::new("redefinition is not allowed in pure mode", vec![Atom::Identifier(name)]))
+    }
+    bound.insert(name);
+    Ok(())
+}
+
+fn parameter_names(params: &Atom) -> Vec<&str> {
+    match *params {
+        Atom::Identifier(ref rest) => vec![rest.as_str()],
+        Atom::List(ref params) => params.iter().filter_map(|param| match *param {
+            Atom::Identifier(ref name) if !name.starts_with("#!") && name != "." => Some(name.as_str()),
+            Atom::List(ref default) => match default.first() {
+                Some(Atom::Identifier(name)) => Some(name.as_str()),
+                _ => None
+            },
+            _ => None
+        }).collect(),
+        _ => vec![]
+    }
+}
+
+fn parameter_defaults(params: &Atom) -> Vec<Atom> {
+    match *params {
+        Atom::List(ref params) => params.iter().filter_map(binding_init).collect(),
+        _ => vec![]
+    }
+}
+
+fn binding_names(bindings: &[Atom]) -> Vec<&str> {
+    bindings.iter().filter_map(|binding| match *binding {
+        Atom::List(ref binding) => match binding.first() {
+            Some(Atom::Identifier(name)) => Some(name.as_str()),
+            _ => None
+        },
+        _ => None
+    }).collect()
+}
+
+fn binding_init(binding: &Atom) -> Option<Atom> {
+    match *binding {
+        Atom::List(ref binding) => binding.get(1).cloned(),
+        _ => None
+    }
+}
+
+fn do_step(spec: &Atom) -> Option<Atom> {
+    match *spec {
+        Atom::List(ref spec) => spec.get(2).cloned(),
+        _ => None
+    }
+}
@@ -1,28 +1,112 @@
-use scope::{ScopeRef};
+use std::rc::Rc;
+use std::slice;
+use continuation::Continuation;
+use port::PortRef;
+use scope::ScopeRef;
+use source::Location;
 
 #[derive(Debug, Clone)]
 pub enum Atom {
     List(Vec<Atom>),
+    // A list of a program to be evaluated, marked with where it was written.
+    // It is otherwise the same as the list.
+    Form(Rc<Location>, Vec<Atom>),
     Integer(i64),
     Identifier(String),
     Quoted(Box<Atom>),
-    Lambda(Closure)
+    String(Rc<str>),
+    // A procedure built into the interpreter, by name.
+    Builtin(&'static str),
+    Lambda(Closure),
+    Continuation(Rc<Continuation>),
+    Condition(Rc<Condition>),
+    Port(PortRef)
 }
 
 #[derive(Debug, Clone)]
 pub struct Closure {
-    pub scope: ScopeRef<Atom>,
-    pub parameters: Vec<String>, 
+    // The name the procedure was first bound to, if any.
+    pub name: Option<String>,
+    pub scope: ScopeRef,
+    pub parameters: Rc<Parameters>,
     pub body: Box<Atom>
 }
 
+// (lambda (required... #!optional optional... . rest) ...), where rest may
+// also be written #!rest rest, and keyword parameters follow #!key. Optional
+// and keyword parameters may be written (name default) to give a default
+// expression, evaluated when the call doesn't supply a value.
+#[derive(Debug, Clone, Default)]
+pub struct Parameters {
+    pub required: Vec<String>,
+    pub optional: Vec<(String, Option<Atom>)>,
+    pub rest: Option<String>,
+    pub keys: Vec<(String, Option<Atom>)>
+}
+
+// What the interpreter raises when something goes wrong, and what `error`
+// raises on behalf of lithp code.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub message: String,
+    pub irritants: Vec<Atom>,
+    // Where the form that raised it was written, if the interpreter raised
+    // it and knows.
+    pub location: Option<Rc<Location>>
+}
+
+impl Atom {
+    // Whether lists, quotes and conditions nest in the atom more than
+    // `max_depth` deep. Unlike printing or comparing atoms, this doesn't
+    // recurse, so it is safe however deep they go.
+    pub fn nests_deeper_than(&self, max_depth: usize) -> bool {
+        let mut atoms = vec![(self, 1)];
+        while let Some((atom, depth)) = atoms.pop() {
+            let items = match *atom {
+                Atom::List(ref items) | Atom::Form(_, ref items) => items.as_slice(),
+                Atom::Quoted(ref atom) => slice::from_ref(&**atom),
+                Atom::Condition(ref condition) => condition.irritants.as_slice(),
+                _ => continue
+            };
+            if depth > max_depth {
+                return true
+            }
+            atoms.extend(items.iter().map(|item| (item, depth + 1)));
+        }
+        false
+    }
+
+    // Whether two atoms are the same object, as `eq?` tells. Lists have no
+    // identity apart from their elements, as they can't be modified, so they
+    // are the same if their elements are.
+    pub fn is_identical(&self, other: &Atom) -> bool {
+        match (self, other) {
+            (Atom::String(a), Atom::String(b)) => Rc::ptr_eq(a, b),
+            (Atom::List(a) | Atom::Form(_, a), Atom::List(b) | Atom::Form(_, b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.is_identical(b))
+            },
+            (Atom::Quoted(a), Atom::Quoted(b)) => a.is_identical(b),
+            _ => self == other
+        }
+    }
+}
+
+// Structural equality, as `equal?` tells. Procedures, continuations,
+// conditions and ports are only equal to themselves.
 impl PartialEq for Atom {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (&Atom::Identifier(ref a), &Atom::Identifier(ref b)) => a == b,
-            (&Atom::Integer(a), &Atom::Integer(b)) => a == b,
-            (&Atom::List(ref a), &Atom::List(ref b)) => a == b,
-            (&Atom::Quoted(ref a), &Atom::Quoted(ref b)) => a == b,
+            (Atom::Identifier(a), Atom::Identifier(b)) => a == b,
+            (Atom::Integer(a), Atom::Integer(b)) => a == b,
+            (Atom::String(a), Atom::String(b)) => a == b,
+            (Atom::List(a) | Atom::Form(_, a), Atom::List(b) | Atom::Form(_, b)) => a == b,
+            (Atom::Quoted(a), Atom::Quoted(b)) => a == b,
+            (Atom::Builtin(a), Atom::Builtin(b)) => a == b,
+            // Each closure has a scope of its own, so this is identity.
+            (Atom::Lambda(a), Atom::Lambda(b)) => a.scope == b.scope,
+            (Atom::Continuation(a), Atom::Continuation(b)) => Rc::ptr_eq(a, b),
+            (Atom::Condition(a), Atom::Condition(b)) => Rc::ptr_eq(a, b),
+            (Atom::Port(a), Atom::Port(b)) => Rc::ptr_eq(a, b),
             _ => false
         }
     }
@@ -0,0 +1,39 @@
+// What a built in procedure lets lithp code do. An interpreter is given a
+// set of these, and built ins needing any it wasn't given are unbound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    // Computing with values, and nothing else.
+    Pure,
+    // Reading from the console or a port.
+    IoRead,
+    // Writing to the console or a port.
+    IoWrite,
+    // Opening, loading and looking for files, including the files of
+    // imported modules.
+    Filesystem,
+    // Controlling the interpreter's own process, such as when it collects
+    // garbage.
+    Process,
+    // Reading clocks.
+    Time
+}
+
+impl Capability {
+    pub const ALL: [Capability; 6] = [Capability::Pure, Capability::IoRead, Capability::IoWrite,
+                                      Capability::Filesystem, Capability::Process, Capability::Time];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Pure => "pure",
+            Capability::IoRead => "io-read",
+            Capability::IoWrite => "io-write",
+            Capability::Filesystem => "filesystem",
+            Capability::Process => "process",
+            Capability::Time => "time"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Capability> {
+        Capability::ALL.iter().cloned().find(|capability| capability.name() == name)
+    }
+}
@@ -0,0 +1,324 @@
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+use atom::Atom;
+use heap::{Trace, Tracer};
+use module::ImportSet;
+use port::PortRef;
+use scope::ScopeRef;
+use source::Location;
+
+// What is left to do with the value of the expression being evaluated. The
+// evaluator keeps these on its own stack rather than the native one, so that
+// the rest of a computation can be captured and resumed as many times as
+// needed. Lists of pending work are stored in reverse so that the next item
+// can be popped off the end.
+#[derive(Debug, Clone)]
+pub enum Frame {
+    // Evaluating a call, written at `site` if it was read from source.
+    Args { scope: ScopeRef, site: Option<Rc<Location>>, pending: Vec<Atom>, evaluated: Vec<Atom> },
+    Body { scope: ScopeRef, pending: Vec<Atom> },
+    Let {
+        scope: ScopeRef,
+        new_scope: ScopeRef,
+        name: String,
+        pending: Vec<(String, Atom)>,
+        body: Vec<Atom>
+    },
+    LetStar {
+        new_scope: ScopeRef,
+        name: String,
+        pending: Vec<(String, Atom)>,
+        body: Vec<Atom>
+    },
+    Define { scope: ScopeRef, name: String },
+    Set { scope: ScopeRef, name: String, site: Option<Rc<Location>> },
+    And { scope: ScopeRef, pending: Vec<Atom> },
+    Or { scope: ScopeRef, pending: Vec<Atom> },
+    Cond { scope: ScopeRef, body: Atom, pending: Vec<Atom> },
+    // Evaluating the initial values or steps of a `do` loop's variables in
+    // `scope`, before binding them in a fresh scope for the next iteration.
+    DoStep {
+        scope: ScopeRef,
+        do_loop: Rc<DoLoop>,
+        name: String,
+        pending: Vec<(String, Atom)>,
+        values: Vec<(String, Atom)>
+    },
+    DoTest { loop_scope: ScopeRef, do_loop: Rc<DoLoop> },
+    DoCommands { loop_scope: ScopeRef, do_loop: Rc<DoLoop> },
+    WindBefore { before: Atom, thunk: Atom, after: Atom },
+    WindBody,
+    WindAfter { value: Atom },
+    // Running the before and after thunks crossed when jumping to `target`.
+    Rewind { steps: Vec<(Atom, Vec<Rc<Winder>>)>, target: Rc<Continuation>, value: Atom },
+    // Installed by `with-exception-handler` for the extent of its thunk.
+    Handler { handler: Atom },
+    // A handler is running for `payload`. Handlers at or above
+    // `handler_index` are not in effect until it returns.
+    Handled { handler_index: usize, payload: Atom, continuable: bool },
+    // The body of a `guard`, which raised objects unwind to.
+    Guard { scope: ScopeRef, var: String, clauses: Vec<Atom>, winders: Vec<Rc<Winder>> },
+    // Receives the raised object once the stack has unwound to a `guard`.
+    Caught { scope: ScopeRef, var: String, clauses: Vec<Atom> },
+    // Choosing a `guard` clause for a raised object bound in `scope`.
+    Catch { scope: ScopeRef, body: Vec<Atom>, pending: Vec<Atom>, payload: Atom },
+    // Evaluating the body of a module, to be registered once it is done.
+    Module { scope: ScopeRef, name: Vec<String>, exports: Vec<String> },
+    // Loading the file that should define `module`, before importing into
+    // `scope` from it and then from the pending import sets.
+    Import { scope: ScopeRef, module: Vec<String>, pending: Vec<ImportSet> },
+    // Calling `func` with the next elements of the lists given to `map` or
+    // `for-each`. `for-each` has no results to collect.
+    Map { func: Atom, pending: Vec<Vec<Atom>>, results: Option<Vec<Atom>> },
+    // Calling `pred` on `current`, which `filter` keeps if it holds.
+    Filter { pred: Atom, current: Atom, pending: Vec<Atom>, kept: Vec<Atom> },
+    // Calling `func` with the value accumulated so far and the next element,
+    // with the accumulated value first if `acc_first`.
+    Fold { func: Atom, pending: Vec<Atom>, acc_first: bool },
+    // Calling `compare` on `key` and `current` for `member`, or the car of
+    // `current` for `assoc`.
+    Find { compare: Atom, key: Atom, current: Atom, pending: Vec<Atom>, assoc: bool },
+    // Calling the comparison of a `sort` on the heads of two runs.
+    Sort { sort: Box<MergeSort> },
+    // Output written without a port goes to `port` while this is on the
+    // stack, for `with-output-to-string`.
+    Output { port: PortRef },
+    // Closes `port` once the procedure it was passed to returns.
+    ClosePort { port: PortRef },
+    // A closure being called, kept so that debuggers and error reports can
+    // see the calls under way. A call in tail position replaces the call it
+    // was made from, so this takes no room in loops.
+    Call { call: Call }
+}
+
+// A call of a closure, by the name it was bound to, with where it was called
+// from if that was written in source, and the arguments it was given. The
+// arguments are only kept for showing the call, and aren't traced: whatever
+// the closure still needs of them is bound in its scope.
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub name: Option<String>,
+    pub site: Option<Rc<Location>>,
+    pub args: Rc<[Atom]>
+}
+
+// (do ((var init step)...) (test result...) command...), evaluated in scope.
+#[derive(Debug)]
+pub struct DoLoop {
+    pub scope: ScopeRef,
+    pub steps: Vec<(String, Atom)>,
+    pub test: Atom,
+    pub result: Vec<Atom>,
+    pub commands: Vec<Atom>
+}
+
+// A stable bottom up merge sort, comparing elements with `less`. Each pass
+// merges the runs left to merge, two at a time, into `merged`.
+#[derive(Debug, Clone)]
+pub struct MergeSort {
+    pub less: Atom,
+    pub runs: Vec<Vec<Atom>>,
+    pub merged: Vec<Vec<Atom>>,
+    // The two runs being merged, reversed so their heads can be popped off
+    // the end, and what has been merged from them so far.
+    pub left: Vec<Atom>,
+    pub right: Vec<Atom>,
+    pub out: Vec<Atom>
+}
+
+// The before and after thunks of an active `dynamic-wind`.
+#[derive(Debug)]
+pub struct Winder {
+    pub before: Atom,
+    pub after: Atom
+}
+
+#[derive(Debug)]
+pub struct Continuation {
+    pub stack: Vec<Frame>,
+    pub winders: Vec<Rc<Winder>>
+}
+
+thread_local! {
+    // The stacks of continuations whose drop is under way and that are left
+    // to drop, while one is.
+    static DROPPING: RefCell<Option<Vec<Vec<Frame>>>> = const { RefCell::new(None) };
+}
+
+// A continuation's stack may hold the last reference to another
+// continuation, whose stack may hold another, so dropping a long chain of
+// them would recurse as deep as the chain. The stacks are dropped one after
+// another from the outermost drop instead.
+impl Drop for Continuation {
+    fn drop(&mut self) {
+        let stack = mem::take(&mut self.stack);
+        let outermost = DROPPING.with(|dropping| match *dropping.borrow_mut() {
+            Some(ref mut stacks) => {
+                stacks.push(stack);
+                None
+            },
+            ref mut dropping => {
+                *dropping = Some(vec![]);
+                Some(stack)
+            }
+        });
+        if let Some(stack) = outermost {
+            drop(stack);
+            while let Some(stack) = DROPPING.with(|dropping| dropping.borrow_mut().as_mut().and_then(Vec::pop)) {
+                drop(stack);
+            }
+            DROPPING.with(|dropping| *dropping.borrow_mut() = None);
+        }
+    }
+}
+
+impl Trace for Frame {
+    fn trace(&self, tracer: &mut Tracer) {
+        match *self {
+            Frame::Args { scope, ref pending, ref evaluated, .. } => {
+                tracer.scope(scope);
+                tracer.atoms(pending);
+                tracer.atoms(evaluated);
+            },
+            Frame::Body { scope, ref pending } |
+            Frame::And { scope, ref pending } |
+            Frame::Or { scope, ref pending } => {
+                tracer.scope(scope);
+                tracer.atoms(pending);
+            },
+            Frame::Let { scope, new_scope, ref pending, ref body, .. } => {
+                tracer.scope(scope);
+                tracer.scope(new_scope);
+                trace_bindings(pending, tracer);
+                tracer.atoms(body);
+            },
+            Frame::LetStar { new_scope, ref pending, ref body, .. } => {
+                tracer.scope(new_scope);
+                trace_bindings(pending, tracer);
+                tracer.atoms(body);
+            },
+            Frame::Define { scope, .. } | Frame::Set { scope, .. } |
+            Frame::Module { scope, .. } | Frame::Import { scope, .. } => tracer.scope(scope),
+            Frame::Cond { scope, ref body, ref pending } => {
+                tracer.scope(scope);
+                tracer.atom(body);
+                tracer.atoms(pending);
+            },
+            Frame::DoStep { scope, ref do_loop, ref pending, ref values, .. } => {
+                tracer.scope(scope);
+                do_loop.trace(tracer);
+                trace_bindings(pending, tracer);
+                trace_bindings(values, tracer);
+            },
+            Frame::DoTest { loop_scope, ref do_loop } |
+            Frame::DoCommands { loop_scope, ref do_loop } => {
+                tracer.scope(loop_scope);
+                do_loop.trace(tracer);
+            },
+            Frame::WindBefore { ref before, ref thunk, ref after } => {
+                tracer.atom(before);
+                tracer.atom(thunk);
+                tracer.atom(after);
+            },
+            Frame::Output { ref port } => tracer.port(port),
+            Frame::WindBody | Frame::ClosePort { .. } | Frame::Call { .. } => (),
+            Frame::WindAfter { ref value } => tracer.atom(value),
+            Frame::Rewind { ref steps, ref target, ref value } => {
+                for (thunk, winders) in steps {
+                    tracer.atom(thunk);
+                    trace_winders(winders, tracer);
+                }
+                tracer.continuation(target);
+                tracer.atom(value);
+            },
+            Frame::Handler { ref handler } => tracer.atom(handler),
+            Frame::Handled { ref payload, .. } => tracer.atom(payload),
+            Frame::Guard { scope, ref clauses, ref winders, .. } => {
+                tracer.scope(scope);
+                tracer.atoms(clauses);
+                trace_winders(winders, tracer);
+            },
+            Frame::Caught { scope, ref clauses, .. } => {
+                tracer.scope(scope);
+                tracer.atoms(clauses);
+            },
+            Frame::Catch { scope, ref body, ref pending, ref payload } => {
+                tracer.scope(scope);
+                tracer.atoms(body);
+                tracer.atoms(pending);
+                tracer.atom(payload);
+            },
+            Frame::Map { ref func, ref pending, ref results } => {
+                tracer.atom(func);
+                for args in pending {
+                    tracer.atoms(args);
+                }
+                if let Some(results) = results {
+                    tracer.atoms(results);
+                }
+            },
+            Frame::Filter { ref pred, ref current, ref pending, ref kept } => {
+                tracer.atom(pred);
+                tracer.atom(current);
+                tracer.atoms(pending);
+                tracer.atoms(kept);
+            },
+            Frame::Fold { ref func, ref pending, .. } => {
+                tracer.atom(func);
+                tracer.atoms(pending);
+            },
+            Frame::Find { ref compare, ref key, ref current, ref pending, .. } => {
+                tracer.atom(compare);
+                tracer.atom(key);
+                tracer.atom(current);
+                tracer.atoms(pending);
+            },
+            Frame::Sort { ref sort } => sort.trace(tracer)
+        }
+    }
+}
+
+impl Trace for DoLoop {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.scope(self.scope);
+        trace_bindings(&self.steps, tracer);
+        tracer.atom(&self.test);
+        tracer.atoms(&self.result);
+        tracer.atoms(&self.commands);
+    }
+}
+
+impl Trace for MergeSort {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.atom(&self.less);
+        for run in self.runs.iter().chain(&self.merged) {
+            tracer.atoms(run);
+        }
+        tracer.atoms(&self.left);
+        tracer.atoms(&self.right);
+        tracer.atoms(&self.out);
+    }
+}
+
+impl Trace for Continuation {
+    fn trace(&self, tracer: &mut Tracer) {
+        for frame in &self.stack {
+            frame.trace(tracer);
+        }
+        trace_winders(&self.winders, tracer);
+    }
+}
+
+pub fn trace_winders(winders: &[Rc<Winder>], tracer: &mut Tracer) {
+    for winder in winders {
+        tracer.atom(&winder.before);
+        tracer.atom(&winder.after);
+    }
+}
+
+fn trace_bindings(bindings: &[(String, Atom)], tracer: &mut Tracer) {
+    for (_, atom) in bindings {
+        tracer.atom(atom);
+    }
+}
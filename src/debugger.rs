@@ -0,0 +1,219 @@
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::rc::Rc;
+use atom::{Atom, Closure};
+use eval::Interpreter;
+use hooks::Hooks;
+use parser::Parser;
+use pretty::{pretty, DEFAULT_WIDTH};
+use scope::ScopeRef;
+use source::Location;
+use tokenizer::tokenize;
+use util::shorten;
+
+const HELP: &str = "\
+step, s           evaluate the next form, stepping into calls
+next, n           evaluate this form, stepping over calls
+finish, f         carry on until the current closure returns
+continue, c       carry on until a breakpoint
+break, b LINE     pause at forms starting on LINE
+break, b NAME     pause when a closure named NAME is called
+delete, d ...     remove a breakpoint, or all of them with no argument
+backtrace, bt     show the closures being called
+locals, l         show the bindings in scope, innermost first
+print, p EXPR     evaluate EXPR where evaluation is paused
+quit, q           stop the program
+An empty line repeats the last command.";
+
+// How far to let evaluation run before pausing again.
+enum Resume {
+    Step,
+    // Until a form is evaluated with at most this many frames waiting.
+    Over(usize),
+    Continue
+}
+
+#[derive(Debug, PartialEq)]
+enum Breakpoint {
+    Line(usize),
+    Name(String)
+}
+
+// The debugger of `lithp --debug`, which pauses before evaluating forms of
+// the program to take commands from the console.
+pub struct Debugger {
+    resume: Resume,
+    breakpoints: Vec<Breakpoint>,
+    // A closure with a breakpoint on its name has been called, so pause at
+    // the first form of its body.
+    entered: bool,
+    // Where the last form evaluated starts, so a breakpoint on a line
+    // pauses once as it is reached rather than at every form on it.
+    last: Option<(Rc<str>, usize)>,
+    command: String
+}
+
+impl Debugger {
+    // A debugger that pauses before the first form.
+    pub fn new() -> Debugger {
+        Debugger {
+            resume: Resume::Step,
+            breakpoints: vec![],
+            entered: false,
+            last: None,
+            command: String::new()
+        }
+    }
+
+    fn should_pause(&mut self, interpreter: &Interpreter, location: &Location) -> bool {
+        let line = location.position.line;
+        let same_line = self.last.as_ref()
+            .is_some_and(|(source, last)| *last == line && *source == location.source);
+        self.last = Some((location.source.clone(), line));
+        let entered = self.entered;
+        self.entered = false;
+        match self.resume {
+            Resume::Step => true,
+            Resume::Over(depth) if interpreter.depth() <= depth => true,
+            _ => entered || (!same_line && self.breakpoints.contains(&Breakpoint::Line(line)))
+        }
+    }
+
+    // Takes commands until one resumes evaluation.
+    fn pause(&mut self, interpreter: &mut Interpreter, scope: ScopeRef, form: &Atom, location: &Location) {
+        println!("{}: {}", location, summary(form));
+        loop {
+            print!("(debug) ");
+            let _ = io::stdout().flush();
+            let mut line = String::new();
+            match io::stdin().lock().read_line(&mut line) {
+                Ok(0) | Err(_) => process::exit(0),
+                Ok(_) => ()
+            }
+            let line = line.trim();
+            if !line.is_empty() {
+                self.command = line.to_string();
+            }
+            let command = self.command.clone();
+            let (name, argument) = match command.split_once(char::is_whitespace) {
+                Some((name, argument)) => (name, argument.trim()),
+                None => (command.as_str(), "")
+            };
+            match name {
+                "step" | "s" => self.resume = Resume::Step,
+                "next" | "n" => self.resume = Resume::Over(interpreter.depth()),
+                "finish" | "f" => self.resume = Resume::Over(interpreter.call_depth()),
+                "continue" | "c" => self.resume = Resume::Continue,
+                "break" | "b" if argument.is_empty() => {
+                    for breakpoint in &self.breakpoints {
+                        println!("{}", describe(breakpoint));
+                    }
+                    continue;
+                },
+                "break" | "b" => {
+                    let breakpoint = breakpoint(argument);
+                    println!("breakpoint at {}", describe(&breakpoint));
+                    if !self.breakpoints.contains(&breakpoint) {
+                        self.breakpoints.push(breakpoint);
+                    }
+                    continue;
+                },
+                "delete" | "d" if argument.is_empty() => {
+                    self.breakpoints.clear();
+                    continue;
+                },
+                "delete" | "d" => {
+                    let breakpoint = breakpoint(argument);
+                    self.breakpoints.retain(|other| *other != breakpoint);
+                    continue;
+                },
+                "backtrace" | "bt" => {
+                    for (i, call) in interpreter.call_stack().iter().enumerate() {
+                        match call.site {
+                            Some(ref site) => println!("#{} {} called at {}", i, summary(call), site),
+                            None => println!("#{} {}", i, summary(call))
+                        }
+                    }
+                    continue;
+                },
+                "locals" | "l" => {
+                    for (depth, bindings) in interpreter.scope_chain(scope).iter().enumerate() {
+                        for (name, value) in bindings {
+                            println!("{}{} = {}", "  ".repeat(depth), name, summary(value));
+                        }
+                    }
+                    continue;
+                },
+                "print" | "p" => {
+                    match read(argument) {
+                        Ok(expression) => match interpreter.eval_in(scope, expression) {
+                            Ok(Atom::Identifier(ref name)) if name.is_empty() => (),
+                            Ok(value) => println!("{}", pretty(&value, DEFAULT_WIDTH)),
+                            Err(error) => println!("Evaluation Error: {}", error)
+                        },
+                        Err(message) => println!("Syntax Error: {}", message)
+                    }
+                    continue;
+                },
+                "quit" | "q" => process::exit(0),
+                "help" | "h" => {
+                    println!("{}", HELP);
+                    continue;
+                },
+                _ => {
+                    println!("unknown command {}, try help", name);
+                    continue;
+                }
+            }
+            return;
+        }
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+impl Hooks for Debugger {
+    fn form(&mut self, interpreter: &mut Interpreter, scope: ScopeRef, form: &Atom, location: &Location) {
+        if self.should_pause(interpreter, location) {
+            self.pause(interpreter, scope, form, location);
+        }
+    }
+
+    fn call(&mut self, closure: &Closure) {
+        if let Some(ref name) = closure.name {
+            self.entered |= self.breakpoints.contains(&Breakpoint::Name(name.clone()));
+        }
+    }
+}
+
+fn breakpoint(argument: &str) -> Breakpoint {
+    match argument.parse() {
+        Ok(line) => Breakpoint::Line(line),
+        Err(_) => Breakpoint::Name(argument.to_string())
+    }
+}
+
+fn describe(breakpoint: &Breakpoint) -> String {
+    match *breakpoint {
+        Breakpoint::Line(line) => format!("line {}", line),
+        Breakpoint::Name(ref name) => name.clone()
+    }
+}
+
+fn read(text: &str) -> Result<Atom, &'static str> {
+    match Parser::new(tokenize(text)?).parse_datum()? {
+        Some(datum) => Ok(datum),
+        None => Err("expected an expression")
+    }
+}
+
+// The start of a value written on one line, for showing where evaluation is
+// and what is bound.
+fn summary<T: Display>(value: &T) -> String {
+    shorten(value.to_string(), 72)
+}
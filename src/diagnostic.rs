@@ -0,0 +1,34 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+// A place in source code, counting lines and columns from one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize
+}
+
+impl Position {
+    pub fn start() -> Position {
+        Position { line: 1, column: 1 }
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}:{}", self.line, self.column)
+    }
+}
+
+// A problem found in source code, and where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub position: Position,
+    pub message: &'static str
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}: {}", self.position, self.message)
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use atom::{Atom, Condition};
+use continuation::Call;
+use source::Location;
+use util::shorten;
+
+// How many calls in a row from the same place a traceback shows in full.
+const REPEATS_SHOWN: usize = 3;
+// How many lines a traceback may have, the rest of a very deep stack being
+// left out of the middle.
+const MAX_TRACEBACK: usize = 40;
+
+// An object raised during evaluation that nothing handled. The interpreter
+// raises conditions for its own errors, but lithp code may raise anything.
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub payload: Atom,
+    // Evaluation ran past one of its limits. Nothing in lithp can handle
+    // this, so it always stops the evaluation.
+    pub resource_exhausted: bool,
+    // The closures being called when it was raised, outermost first, once
+    // nothing handled it.
+    pub trace: Vec<Call>
+}
+
+impl Error {
+    pub fn new(message: &str, irritants: Vec<Atom>) -> Error {
+        Error::located(message, irritants, None)
+    }
+
+    // An error raised by the form written at `location`.
+    pub fn located(message: &str, irritants: Vec<Atom>, location: Option<Rc<Location>>) -> Error {
+        Error::raise(Atom::Condition(Rc::new(Condition {
+            message: message.to_string(),
+            irritants,
+            location
+        })))
+    }
+
+    pub fn raise(payload: Atom) -> Error {
+        Error { payload, resource_exhausted: false, trace: vec![] }
+    }
+
+    // `resource` is what ran out: steps, cells or time.
+    pub fn exhausted(resource: &str) -> Error {
+        Error {
+            resource_exhausted: true,
+            ..Error::new("resource exhausted", vec![Atom::Identifier(resource.to_string())])
+        }
+    }
+}
+
+impl From<&'static str> for Error {
+    fn from(message: &'static str) -> Error {
+        Error::new(message, vec![])
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        match self.payload {
+            Atom::Condition(ref condition) => condition.fmt(fmt),
+            ref payload => write!(fmt, "uncaught raise of {}", payload)
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        if let Some(ref location) = self.location {
+            write!(fmt, "{}: ", location)?;
+        }
+        fmt.write_str(&self.message)?;
+        for irritant in &self.irritants {
+            write!(fmt, " {}", irritant)?;
+        }
+        Ok(())
+    }
+}
+
+// A call written as the expression making it, with the arguments it was
+// given.
+impl Display for Call {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "({}", self.name.as_deref().unwrap_or("<lambda>"))?;
+        for arg in self.args.iter() {
+            write!(fmt, " {}", arg)?;
+        }
+        fmt.write_str(")")
+    }
+}
+
+// The calls an error was raised in, written most recent last like Python's
+// tracebacks. Long runs of calls from the same place, as in a deep
+// recursion, are cut down to their first and last calls, and a stack still
+// too deep to show has its middle left out.
+pub struct Traceback<'a>(pub &'a [Call]);
+
+impl<'a> Display for Traceback<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        let line = |call: &Call| {
+            let text = shorten(call.to_string(), 72);
+            match call.site {
+                Some(ref site) => format!("  {}: {}", site, text),
+                None => format!("  {}", text)
+            }
+        };
+        let mut lines = vec![];
+        let mut calls = self.0;
+        while let Some(first) = calls.first() {
+            let run = calls.iter()
+                .take_while(|call| call.name == first.name && call.site == first.site)
+                .count();
+            if run > REPEATS_SHOWN + 1 {
+                lines.extend(calls[..REPEATS_SHOWN - 1].iter().map(line));
+                lines.push(format!("  [{} more calls from the same place]", run - REPEATS_SHOWN));
+                lines.push(line(&calls[run - 1]));
+            } else {
+                lines.extend(calls[..run].iter().map(line));
+            }
+            calls = &calls[run..];
+        }
+        if lines.len() > MAX_TRACEBACK {
+            let half = MAX_TRACEBACK / 2;
+            let omitted = lines.len() - 2 * half;
+            lines.splice(half..lines.len() - half, Some(format!("  [{} more lines]", omitted)));
+        }
+        writeln!(fmt, "Traceback (most recent call last):")?;
+        for line in lines {
+            writeln!(fmt, "{}", line)?;
+        }
+        Ok(())
+    }
+}
@@ -1,279 +1,1863 @@
-use atom::{Atom, Closure};
-use scope::{ScopeRef};
+use std::collections::HashSet;
+use std::mem;
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::slice;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use analyze::check_pure;
+use atom::{Atom, Closure, Parameters};
+use capability::Capability::{self, Filesystem, IoRead, IoWrite, Process, Pure, Time};
+use continuation::{trace_winders, Call, Continuation, DoLoop, Frame, MergeSort, Winder};
+use diagnostic::{Diagnostic, Position};
+use error::Error;
+use heap::{Heap, Trace, Tracer};
+use hooks::Hooks;
+use module::{module_name, module_path, name_atom, ImportSet, Module, Modules};
+use parser::{parse_program, Parsed, Parser, DEFAULT_MAX_DEPTH};
+use port::{Io, Port, PortRef, StdIo};
+use pretty::{pretty, DEFAULT_WIDTH};
+use printer::Displayed;
+use profile::{Procedure, Profiler};
+use reader::Reader;
+use scope::ScopeRef;
+use source::{locate, Location};
+use tokenizer::tokenize;
 use util::prepend;
-use scope::{new_child_scope, new_scope};
 
-const BUILT_INS: [&'static str; 18] = ["define", "+", "-", "*", "/", "cons",
-                                       "car", "cdr", "list", "let", "let*",
-                                       "lambda", "=", "!=", ">",
-                                       "<", "equal?", "not"];
+// A built in procedure, called with its own name and its arguments.
+type BuiltIn = fn(&mut Interpreter, &'static str, Vec<Atom>) -> StepResult;
+
+// Every built in, with the capability it needs to be bound. This is both
+// what names resolve to and what `--list-built-ins` lists, so the two can't
+// disagree.
+const BUILT_INS: &[(&str, Capability, BuiltIn)] = &[
+    ("+", Pure, |_, _, args| value(math(0, i64::checked_add, &args))),
+    ("-", Pure, |_, _, args| value(math_first(i64::checked_sub, &args))),
+    ("*", Pure, |_, _, args| value(math(1, i64::checked_mul, &args))),
+    ("/", Pure, |_, _, args| value(divide(&args))),
+    ("cons", Pure, |interpreter, _, args| interpreter.within_depth(cons(&args)?).map(Control::Return)),
+    ("car", Pure, |_, _, args| value(car(&args))),
+    ("cdr", Pure, |_, _, args| value(cdr(&args))),
+    ("list", Pure, |interpreter, _, args| interpreter.within_depth(list(&args)?).map(Control::Return)),
+    ("=", Pure, |_, _, args| value(eq(&args))),
+    (">", Pure, |_, _, args| value(gt(&args))),
+    ("<", Pure, |_, _, args| value(lt(&args))),
+    ("equal?", Pure, |_, _, args| value(equal(&args))),
+    ("not", Pure, |_, _, args| value(not(&args))),
+    ("gc", Process, |interpreter, _, args| value(interpreter.gc(&args))),
+    ("gc-stats", Process, |interpreter, _, args| value(interpreter.gc_stats(&args))),
+    ("call/cc", Pure, |interpreter, _, args| interpreter.call_cc(args)),
+    ("call-with-current-continuation", Pure, |interpreter, _, args| interpreter.call_cc(args)),
+    ("dynamic-wind", Pure, |interpreter, _, args| interpreter.dynamic_wind(args)),
+    ("error", Pure, |_, _, args| Err(make_error(args)?)),
+    ("raise", Pure, |_, _, args| Err(Error::raise(single(args)?))),
+    ("raise-continuable", Pure, |interpreter, _, args| interpreter.signal(single(args)?, true)),
+    ("with-exception-handler", Pure, |interpreter, _, args| interpreter.with_exception_handler(args)),
+    ("error-object?", Pure, |_, _, args| value(error_object(&args))),
+    ("error-object-message", Pure, |_, _, args| value(error_object_message(&args))),
+    ("error-object-irritants", Pure, |_, _, args| value(error_object_irritants(&args))),
+    ("apply", Pure, |interpreter, _, args| interpreter.apply_spread(args)),
+    ("read", IoRead, |interpreter, _, args| value(interpreter.read(&args))),
+    ("read-string", Pure, |interpreter, _, args| value(read_string(&args, interpreter.options.max_depth))),
+    ("write", IoWrite, |interpreter, _, args| interpreter.write(&args, false).map(Control::Return)),
+    ("display", IoWrite, |interpreter, _, args| interpreter.write(&args, true).map(Control::Return)),
+    ("newline", IoWrite, |interpreter, _, args| interpreter.newline(&args).map(Control::Return)),
+    ("eof-object", Pure, |_, _, _| Ok(Control::Return(eof_object()))),
+    ("eof-object?", Pure, |_, _, args| value(is_eof_object(&args))),
+    ("pp", IoWrite, |interpreter, _, args| interpreter.pp(&args).map(Control::Return)),
+    ("load", Filesystem, |interpreter, _, args| interpreter.load(args)),
+    ("map", Pure, |interpreter, _, args| interpreter.map(args, true)),
+    ("for-each", Pure, |interpreter, _, args| interpreter.map(args, false)),
+    ("filter", Pure, |interpreter, _, args| interpreter.filter(args)),
+    ("reduce", Pure, |interpreter, name, args| interpreter.fold(name, args)),
+    ("fold-left", Pure, |interpreter, name, args| interpreter.fold(name, args)),
+    ("fold-right", Pure, |interpreter, name, args| interpreter.fold(name, args)),
+    ("append", Pure, |_, _, args| value(append(&args))),
+    ("reverse", Pure, |_, _, args| value(reverse(&args))),
+    ("length", Pure, |_, _, args| value(length(&args))),
+    ("list-ref", Pure, |_, _, args| value(list_ref(&args))),
+    ("member", Pure, |interpreter, _, args| interpreter.find(args, false)),
+    ("assoc", Pure, |interpreter, _, args| interpreter.find(args, true)),
+    ("sort", Pure, |interpreter, _, args| interpreter.sort(args)),
+    ("iota", Pure, |_, _, args| value(iota(&args))),
+    ("number?", Pure, |_, _, args| value(is_type(&args, |arg| matches!(*arg, Atom::Integer(_))))),
+    ("integer?", Pure, |_, _, args| value(is_type(&args, |arg| matches!(*arg, Atom::Integer(_))))),
+    ("string?", Pure, |_, _, args| value(is_type(&args, |arg| matches!(*arg, Atom::String(_))))),
+    ("symbol?", Pure, |_, _, args| value(is_type(&args, is_symbol))),
+    ("keyword?", Pure, |_, _, args| value(is_type(&args, is_keyword))),
+    ("boolean?", Pure, |_, _, args| value(is_type(&args, is_boolean))),
+    ("list?", Pure, |_, _, args| value(is_type(&args, |arg| matches!(*arg, Atom::List(_))))),
+    ("pair?", Pure, |_, _, args| value(is_type(&args, is_pair))),
+    ("null?", Pure, |_, _, args| value(is_type(&args, is_null))),
+    ("procedure?", Pure, |_, _, args| value(is_type(&args, is_procedure))),
+    ("procedure-arity", Pure, |_, _, args| value(procedure_arity(&args))),
+    ("procedure-name", Pure, |_, _, args| value(procedure_name(&args))),
+    ("eq?", Pure, |_, _, args| value(identical(&args))),
+    ("eqv?", Pure, |_, _, args| value(identical(&args))),
+    ("read-line", IoRead, |interpreter, _, args| value(interpreter.read_line(&args))),
+    ("open-input-file", Filesystem, |interpreter, _, args| {
+        interpreter.open_file(&args, false).map(|port| Control::Return(Atom::Port(port)))
+    }),
+    ("open-output-file", Filesystem, |interpreter, _, args| {
+        interpreter.open_file(&args, true).map(|port| Control::Return(Atom::Port(port)))
+    }),
+    ("close-port", Pure, |_, _, args| value(close_port(&args))),
+    ("close-input-port", Pure, |_, _, args| value(close_port(&args))),
+    ("close-output-port", Pure, |_, _, args| value(close_port(&args))),
+    ("with-output-to-string", Pure, |interpreter, _, args| interpreter.with_output_to_string(args)),
+    ("call-with-input-file", Filesystem, |interpreter, _, args| interpreter.call_with_input_file(args)),
+    ("file-exists?", Filesystem, |interpreter, _, args| value(interpreter.file_exists(&args))),
+    ("input-port?", Pure, |_, _, args| value(is_type(&args, is_input_port))),
+    ("output-port?", Pure, |_, _, args| value(is_type(&args, is_output_port))),
+    ("current-second", Time, |_, _, args| value(current_second(&args))),
+    ("current-jiffy", Time, |_, _, args| value(current_jiffy(&args))),
+    ("jiffies-per-second", Time, |_, _, args| value(jiffies_per_second(&args))),
+];
+
+// Procedures written in lithp itself, evaluated into the global scope before
+// any program unless disabled.
+const PRELUDE: &str = include_str!("prelude.lithp");
 
 fn lithp_true() -> Atom { Atom::Identifier("#t".to_string()) }
 fn lithp_false() -> Atom { Atom::Identifier("#f".to_string()) }
+fn unspecified() -> Atom { Atom::Identifier("".to_string()) }
+fn eof_object() -> Atom { Atom::Identifier("#!eof".to_string()) }
 
 type BasicResult<T> = Result<T, &'static str>;
 
-pub fn eval(atom: Atom) -> Result<Atom, &'static str> {
-    let root_scope = new_scope();
-    {
-        let mut scope_borrow = (*root_scope).borrow_mut();
-        scope_borrow.set_local("#t", lithp_true());
-        scope_borrow.set_local("#f", lithp_false());
+macro_rules! extract {
+    ( $t:path, $cdr:expr ) => {{
+        let cdr = $cdr;
+        let mut result = Vec::with_capacity(cdr.len());
+        for atom in cdr {
+            match atom {
+                $t(val) => result.push(val.clone()),
+                _ => return Err("invalid format".into())
+            }
+        }
+        result
+    }}
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    // Reject top level forms that use set! or define a name twice in one
+    // scope before evaluating them.
+    pub pure: bool,
+    // How deeply lists and quotes may nest, in what `read` and `read-string`
+    // read and in lists built by a program.
+    pub max_depth: usize,
+    // The directories `load` and `import` look for files in, in order.
+    pub search_path: Vec<PathBuf>,
+    // Evaluate the prelude before anything else.
+    pub prelude: bool,
+    pub limits: Limits,
+    // What lithp code may do. Built ins needing anything else are unbound.
+    pub capabilities: HashSet<Capability>
+}
+
+// How much evaluation may use, counted from when the interpreter is made,
+// once the prelude has been evaluated, or from the last `reset_limits`. Past
+// any of these it stops with a "resource exhausted" error that lithp code
+// can't catch. None is no limit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    // Steps the evaluator takes, each evaluating an expression or returning
+    // a value to the frame waiting for it.
+    pub steps: Option<u64>,
+    // Scopes live on the heap at once, which every call, `let` and closure
+    // allocates.
+    pub cells: Option<usize>,
+    // Checked whenever a procedure is called and each time round a `do`.
+    pub time: Option<Duration>
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            pure: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            search_path: vec![PathBuf::from(".")],
+            prelude: true,
+            limits: Limits::default(),
+            capabilities: Capability::ALL.iter().cloned().collect()
+        }
+    }
+}
+
+// The next step for the evaluator: either evaluate an expression, or hand a
+// value to the frame on top of the stack.
+enum Control {
+    Eval(ScopeRef, Atom),
+    Return(Atom)
+}
+
+type StepResult = Result<Control, Error>;
+
+pub struct Interpreter {
+    options: Options,
+    heap: Heap,
+    root_scope: ScopeRef,
+    stack: Vec<Frame>,
+    winders: Vec<Rc<Winder>>,
+    modules: Modules,
+    io: Box<dyn Io>,
+    steps: u64,
+    deadline: Option<Instant>,
+    // Where `read` reads from without a port, opened on the console when
+    // first needed.
+    input: Option<Reader<Box<dyn BufRead>>>,
+    hooks: Option<Box<dyn Hooks>>,
+    profiler: Option<Profiler>,
+    // The stacks and winders of evaluations paused while hooks evaluate
+    // something of their own.
+    suspended: Vec<(Vec<Frame>, Vec<Rc<Winder>>)>,
+    // The forms, and the scopes they are in, that evaluation is paused
+    // before while hooks run. Nothing else refers to them meanwhile.
+    paused: Vec<(ScopeRef, Atom)>,
+    // Values the embedder has rooted, by the index of their `Root`.
+    rooted: Vec<Option<Atom>>
+}
+
+// A value rooted with `Interpreter::root`, which the collector keeps alive,
+// along with every scope it refers to, until it is released.
+#[derive(Debug)]
+pub struct Root(usize);
+
+// Everything the collector must treat as live while a program is running.
+struct Roots<'a> {
+    root_scope: ScopeRef,
+    modules: &'a Modules,
+    control: Option<&'a Control>,
+    stack: &'a [Frame],
+    winders: &'a [Rc<Winder>],
+    suspended: &'a [(Vec<Frame>, Vec<Rc<Winder>>)],
+    paused: &'a [(ScopeRef, Atom)],
+    rooted: &'a [Option<Atom>],
+    // Values a built in is working with.
+    held: &'a [Atom]
+}
+
+impl<'a> Trace for Roots<'a> {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.scope(self.root_scope);
+        self.modules.trace(tracer);
+        match self.control {
+            Some(&Control::Eval(scope, ref atom)) => {
+                tracer.scope(scope);
+                tracer.atom(atom);
+            },
+            Some(Control::Return(value)) => tracer.atom(value),
+            None => ()
+        }
+        for frame in self.stack {
+            frame.trace(tracer);
+        }
+        trace_winders(self.winders, tracer);
+        for (stack, winders) in self.suspended {
+            for frame in stack {
+                frame.trace(tracer);
+            }
+            trace_winders(winders, tracer);
+        }
+        for (scope, form) in self.paused {
+            tracer.scope(*scope);
+            tracer.atom(form);
+        }
+        for value in self.rooted.iter().flatten() {
+            tracer.atom(value);
+        }
+        tracer.atoms(self.held);
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::with_options(Options::default())
+    }
+
+    pub fn with_options(options: Options) -> Interpreter {
+        Interpreter::with_io(options, Box::new(StdIo))
+    }
+
+    // An interpreter whose console and files are whatever `io` makes them.
+    pub fn with_io(options: Options, io: Box<dyn Io>) -> Interpreter {
+        let mut heap = Heap::new();
+        let root_scope = heap.new_scope(None);
+        heap.set_local(root_scope, "#t", lithp_true());
+        heap.set_local(root_scope, "#f", lithp_false());
+        let modules = Modules::new(options.search_path.clone());
+        let mut interpreter = Interpreter {
+            options,
+            heap,
+            root_scope,
+            stack: Vec::new(),
+            winders: Vec::new(),
+            modules,
+            io,
+            steps: 0,
+            deadline: None,
+            input: None,
+            hooks: None,
+            profiler: None,
+            suspended: Vec::new(),
+            paused: Vec::new(),
+            rooted: Vec::new()
+        };
+        // The prelude doesn't count against the limits.
+        let limits = mem::take(&mut interpreter.options.limits);
+        if interpreter.options.prelude {
+            interpreter.load_prelude();
+        }
+        interpreter.options.limits = limits;
+        interpreter.reset_limits();
+        interpreter
+    }
+
+    // Starts counting steps and time afresh, as a REPL does before each form
+    // so that the time spent waiting for input doesn't count.
+    pub fn reset_limits(&mut self) {
+        self.steps = 0;
+        // A timeout too long to reach is as good as none.
+        self.deadline = self.options.limits.time.and_then(|time| Instant::now().checked_add(time));
+    }
+
+    fn load_prelude(&mut self) {
+        for form in located(parse_program(PRELUDE, DEFAULT_MAX_DEPTH), "<prelude>") {
+            self.eval_top_level(form).expect("the prelude failed to evaluate");
+        }
+    }
+
+    // Measures every call from now on, for `profiler` to report.
+    pub fn start_profiling(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    // Has `hooks` called as evaluation goes from now on.
+    pub fn set_hooks(&mut self, hooks: Box<dyn Hooks>) {
+        self.hooks = Some(hooks);
+    }
+
+    // Evaluates one top level form in the global scope, where what it
+    // defines stays for the forms evaluated after it. A procedure in the value
+    // returned stays callable only while something bound in the interpreter
+    // refers to it, unless it is rooted.
+    pub fn eval_form(&mut self, form: Atom) -> Result<Atom, Error> {
+        self.check(slice::from_ref(&form))?;
+        self.eval_top_level(form)
+    }
+
+    // Keeps `value` from being collected, along with the scopes it refers to,
+    // until it is released, so that it can be held outside the interpreter
+    // and passed back in later.
+    pub fn root(&mut self, value: Atom) -> Root {
+        match self.rooted.iter().position(Option::is_none) {
+            Some(index) => {
+                self.rooted[index] = Some(value);
+                Root(index)
+            },
+            None => {
+                self.rooted.push(Some(value));
+                Root(self.rooted.len() - 1)
+            }
+        }
+    }
+
+    pub fn rooted(&self, root: &Root) -> &Atom {
+        self.rooted[root.0].as_ref().unwrap()
+    }
+
+    // Lets a rooted value be collected once nothing else refers to it,
+    // handing it back.
+    pub fn release(&mut self, root: Root) -> Atom {
+        self.rooted[root.0].take().unwrap()
+    }
+
+    // Reads the next top level form from standard input, sharing the input
+    // with `read`, so a program read from standard input can read what
+    // follows it.
+    pub fn read_form(&mut self) -> Result<Option<Atom>, Diagnostic> {
+        let form = self.input()
+            .map_err(|message| Diagnostic { position: Position::start(), message })?
+            .read_spanned()?;
+        Ok(form.map(|(form, span)| locate(form, &span, &Rc::from("<stdin>"))))
+    }
+
+    // Evaluates `form` in `scope` apart from the evaluation under way, which
+    // carries on as it was afterwards. This is for hooks, which are called
+    // with evaluation paused.
+    pub fn eval_in(&mut self, scope: ScopeRef, form: Atom) -> Result<Atom, Error> {
+        let stack = mem::take(&mut self.stack);
+        let winders = mem::take(&mut self.winders);
+        self.suspended.push((stack, winders));
+        let result = self.run(Control::Eval(scope, form));
+        let (stack, winders) = self.suspended.pop().unwrap();
+        self.stack = stack;
+        self.winders = winders;
+        result
+    }
+
+    // The bindings of `scope` and of the scopes it is nested in, innermost
+    // first and each sorted by name, leaving out the global scope.
+    pub fn scope_chain(&self, scope: ScopeRef) -> Vec<Vec<(String, Atom)>> {
+        let mut chain = vec![];
+        let mut current = Some(scope);
+        while let Some(scope) = current.filter(|&scope| scope != self.root_scope) {
+            let scope = self.heap.scope(scope);
+            let mut bindings: Vec<_> = scope.names().into_iter().map(|name| {
+                let value = scope.get_local(&name).unwrap();
+                (name, value)
+            }).collect();
+            bindings.sort_by(|a, b| a.0.cmp(&b.0));
+            chain.push(bindings);
+            current = scope.parent;
+        }
+        chain
+    }
+
+    // The closures being called, innermost first.
+    pub fn call_stack(&self) -> Vec<&Call> {
+        self.stack.iter().rev().filter_map(|frame| match *frame {
+            Frame::Call { ref call } => Some(call),
+            _ => None
+        }).collect()
+    }
+
+    // How many frames are waiting on what is being evaluated.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // The depth the innermost closure being called returns to.
+    pub fn call_depth(&self) -> usize {
+        self.stack.iter().rposition(|frame| matches!(*frame, Frame::Call { .. })).unwrap_or(0)
+    }
+
+    // Checks forms about to be evaluated at the top level, which in pure mode
+    // rejects any that would modify a binding.
+    pub fn check(&mut self, forms: &[Atom]) -> Result<(), Error> {
+        if self.options.pure {
+            let bound: HashSet<String> = self.heap.scope(self.root_scope).names().into_iter().collect();
+            check_pure(forms, bound, self.modules.exports())?;
+        }
+        Ok(())
+    }
+
+    fn eval_top_level(&mut self, form: Atom) -> Result<Atom, Error> {
+        let result = self.run(Control::Eval(self.root_scope, form));
+        if result.is_err() {
+            self.stack.clear();
+            self.winders.clear();
+            self.profile_returns();
+        }
+        result
+    }
+
+    fn run(&mut self, mut control: Control) -> Result<Atom, Error> {
+        loop {
+            if self.heap.should_collect() {
+                self.collect_garbage(Some(&control), &[]);
+            }
+            if let Err(error) = self.check_limits(&control) {
+                return Err(self.traced(error))
+            }
+            let step = match control {
+                Control::Eval(scope, atom) => self.eval_atom(scope, atom),
+                Control::Return(value) => match self.stack.pop() {
+                    Some(frame) => self.resume(frame, value),
+                    None => return Ok(value)
+                }
+            };
+            control = match step {
+                Ok(control) => control,
+                Err(error) if error.resource_exhausted => return Err(self.traced(error)),
+                Err(error) => match self.signal(error.payload, false) {
+                    Ok(control) => control,
+                    Err(error) => return Err(self.traced(error))
+                }
+            };
+        }
+    }
+
+    // Notes the calls under way on an error nothing handled, for reporting
+    // where it happened.
+    fn traced(&self, error: Error) -> Error {
+        let mut trace: Vec<Call> = self.call_stack().into_iter().cloned().collect();
+        trace.reverse();
+        Error { trace, ..error }
+    }
+
+    // The built ins lithp code can reach.
+    pub fn allowed_built_ins(&self) -> Vec<&'static str> {
+        BUILT_INS.iter().filter(|built_in| self.allows(built_in.1)).map(|built_in| built_in.0).collect()
+    }
+
+    // The built ins left unbound, for want of a capability.
+    pub fn denied_built_ins(&self) -> Vec<&'static str> {
+        BUILT_INS.iter().filter(|built_in| !self.allows(built_in.1)).map(|built_in| built_in.0).collect()
+    }
+
+    fn allows(&self, capability: Capability) -> bool {
+        self.options.capabilities.contains(&capability)
+    }
+
+    fn check_limits(&mut self, control: &Control) -> Result<(), Error> {
+        self.steps += 1;
+        if self.options.limits.steps.is_some_and(|steps| self.steps > steps) {
+            return Err(Error::exhausted("steps"))
+        }
+        if let Some(cells) = self.options.limits.cells {
+            // Only what is still reachable counts.
+            if self.heap.cells() > cells {
+                self.collect_garbage(Some(control), &[]);
+                if self.heap.cells() > cells {
+                    return Err(Error::exhausted("cells"))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Fails if lists, quotes and conditions nest in `value` deeper than the
+    // reader would read them, as printing, comparing, copying and dropping
+    // values recurse as deep as they nest.
+    fn within_depth(&self, value: Atom) -> Result<Atom, Error> {
+        if value.nests_deeper_than(self.options.max_depth) {
+            return Err(Error::from("maximum nesting depth exceeded"))
+        }
+        Ok(value)
+    }
+
+    // Counts the cells a built in is about to allocate, failing first if
+    // that would go over the limit even once garbage is collected. `held` is
+    // what the built in is working with, which the collection must keep.
+    fn reserve(&mut self, cells: usize, held: &[Atom]) -> Result<(), Error> {
+        if let Some(limit) = self.options.limits.cells {
+            if self.heap.cells().saturating_add(cells) > limit {
+                self.collect_garbage(None, held);
+                if self.heap.cells().saturating_add(cells) > limit {
+                    return Err(Error::exhausted("cells"))
+                }
+            }
+        }
+        self.heap.allocate_cells(cells);
+        Ok(())
+    }
+
+    fn check_deadline(&self) -> Result<(), Error> {
+        match self.deadline {
+            Some(deadline) if Instant::now() > deadline => Err(Error::exhausted("time")),
+            _ => Ok(())
+        }
+    }
+
+    // Hands a raised object to the innermost handler or `guard` in effect,
+    // failing with it if there is none.
+    fn signal(&mut self, payload: Atom, continuable: bool) -> StepResult {
+        // Conditions can hold conditions, so may nest as deep as lists.
+        let mut payload = match self.within_depth(payload) {
+            Ok(payload) => payload,
+            Err(error) => error.payload
+        };
+        let mut index = self.stack.len();
+        while index > 0 {
+            index -= 1;
+            let handler = match self.stack[index] {
+                Frame::Handled { handler_index, .. } => {
+                    index = handler_index;
+                    continue;
+                },
+                Frame::Handler { ref handler } => handler.clone(),
+                Frame::Guard { .. } => return self.unwind_to_guard(index, payload),
+                _ => continue
+            };
+            self.stack.push(Frame::Handled {
+                handler_index: index,
+                payload: payload.clone(),
+                continuable
+            });
+            match self.apply(handler, vec![payload.clone()]) {
+                Ok(control) => return Ok(control),
+                // Running out of a resource can't be handled.
+                Err(error) if error.resource_exhausted => return Err(error),
+                // Raised by the handler itself, so look for one further out.
+                Err(error) => {
+                    payload = error.payload;
+                    index = self.stack.len();
+                }
+            }
+        }
+        Err(Error::raise(payload))
+    }
+
+    fn unwind_to_guard(&mut self, index: usize, payload: Atom) -> StepResult {
+        let (scope, var, clauses, winders) = match self.stack[index] {
+            Frame::Guard { scope, ref var, ref clauses, ref winders } => {
+                (scope, var.clone(), clauses.clone(), winders.clone())
+            },
+            _ => unreachable!()
+        };
+        let mut stack = self.stack[..index].to_vec();
+        stack.push(Frame::Caught { scope, var, clauses });
+        self.throw(Rc::new(Continuation { stack, winders }), vec![payload])
+    }
+
+    fn collect_garbage(&mut self, control: Option<&Control>, held: &[Atom]) -> usize {
+        let roots = Roots {
+            root_scope: self.root_scope,
+            modules: &self.modules,
+            control,
+            stack: &self.stack,
+            winders: &self.winders,
+            suspended: &self.suspended,
+            paused: &self.paused,
+            rooted: &self.rooted,
+            held
+        };
+        self.heap.collect(&roots)
+    }
+
+    // Evaluates a body in order, leaving the last expression in tail position.
+    fn eval_atoms(&mut self, scope: ScopeRef, mut atoms: Vec<Atom>) -> StepResult {
+        if atoms.is_empty() { return Err(Error::from("eval atoms on empty list")) }
+        atoms.reverse();
+        let first = atoms.pop().unwrap();
+        if !atoms.is_empty() {
+            self.stack.push(Frame::Body { scope, pending: atoms });
+        }
+        Ok(Control::Eval(scope, first))
+    }
+
+    fn eval_atom(&mut self, scope: ScopeRef, atom: Atom) -> StepResult {
+        match atom {
+            Atom::Quoted(value) => Ok(Control::Return(*value)),
+            Atom::Integer(_) | Atom::String(_) | Atom::Builtin(_) | Atom::Lambda(_) |
+            Atom::Continuation(_) | Atom::Condition(_) | Atom::Port(_) => Ok(Control::Return(atom)),
+            Atom::Identifier(_) if is_keyword(&atom) => Ok(Control::Return(atom)),
+            Atom::Identifier(ref name) => self.try_get(scope, name).map(Control::Return),
+            Atom::List(atoms) => self.eval_list(scope, atoms, None),
+            Atom::Form(location, atoms) => {
+                if self.hooks.is_some() {
+                    let form = Atom::Form(location.clone(), atoms.clone());
+                    self.paused.push((scope, form.clone()));
+                    self.hook(|hooks, interpreter| hooks.form(interpreter, scope, &form, &location));
+                    self.paused.pop();
+                }
+                self.eval_list(scope, atoms, Some(location))
+            }
+        }
+    }
+
+    // Hands the hooks this interpreter, if there are any. They are taken out
+    // meanwhile, so nothing they evaluate calls them again.
+    fn hook<F>(&mut self, f: F) where F: FnOnce(&mut dyn Hooks, &mut Interpreter) {
+        if let Some(mut hooks) = self.hooks.take() {
+            f(&mut *hooks, self);
+            self.hooks = Some(hooks);
+        }
+    }
+
+    // Evaluates a special form or call, written at `site` if it was read
+    // from source.
+    fn eval_list(&mut self, scope: ScopeRef, atoms: Vec<Atom>, site: Option<Rc<Location>>) -> StepResult {
+        match atoms.split_first() {
+            // Macros / syntax rules, which have special evaluation
+            Some((Atom::Identifier(x), cdr)) if x == "quote" => eval_quote(cdr),
+            Some((Atom::Identifier(x), cdr)) if x == "let" => self.eval_let(scope, cdr),
+            Some((Atom::Identifier(x), cdr)) if x == "let*" => self.eval_let_star(scope, cdr),
+            Some((Atom::Identifier(x), cdr)) if x == "letrec" || x == "letrec*" => {
+                self.eval_letrec(scope, cdr)
+            },
+            Some((Atom::Identifier(x), cdr)) if x == "do" => self.eval_do(scope, cdr),
+            Some((Atom::Identifier(x), cdr)) if x == "define" => self.eval_define(scope, cdr),
+            Some((Atom::Identifier(x), cdr)) if x == "set!" => self.eval_set(scope, cdr, site),
+            Some((Atom::Identifier(x), cdr)) if x == "lambda" => self.eval_lambda(scope, cdr),
+            Some((Atom::Identifier(x), cdr)) if x == "define-module" => self.eval_define_module(cdr),
+            Some((Atom::Identifier(x), cdr)) if x == "import" => self.eval_import(scope, cdr),
+            // For short circuiting
+            Some((Atom::Identifier(x), cdr)) if x == "and" => self.eval_and(scope, cdr),
+            Some((Atom::Identifier(x), cdr)) if x == "or" => self.eval_or(scope, cdr),
+            Some((Atom::Identifier(x), cdr)) if x == "cond" => self.eval_cond(scope, cdr),
+            Some((Atom::Identifier(x), cdr)) if x == "guard" => self.eval_guard(scope, cdr),
+            Some(_) => {
+                let mut pending = atoms;
+                pending.reverse();
+                let first = pending.pop().unwrap();
+                let evaluated = Vec::with_capacity(pending.len() + 1);
+                self.stack.push(Frame::Args { scope, site, pending, evaluated });
+                Ok(Control::Eval(scope, first))
+            },
+            None => Err(Error::from("invalid empty expression"))
+        }
+    }
+
+    // Continues the computation described by `frame` now that the expression
+    // it was waiting on has produced `value`.
+    fn resume(&mut self, frame: Frame, value: Atom) -> StepResult {
+        match frame {
+            Frame::Args { scope, site, mut pending, mut evaluated } => {
+                evaluated.push(value);
+                match pending.pop() {
+                    Some(next) => {
+                        self.stack.push(Frame::Args { scope, site, pending, evaluated });
+                        Ok(Control::Eval(scope, next))
+                    },
+                    None => {
+                        let func = evaluated.remove(0);
+                        self.apply_at(func, evaluated, site)
+                    }
+                }
+            },
+            Frame::Call { .. } => {
+                self.profile_returns();
+                Ok(Control::Return(value))
+            },
+            Frame::Body { scope, mut pending } => {
+                let next = pending.pop().unwrap();
+                if !pending.is_empty() {
+                    self.stack.push(Frame::Body { scope, pending });
+                }
+                Ok(Control::Eval(scope, next))
+            },
+            Frame::Let { scope, new_scope, name, pending, body } => {
+                self.heap.set_local(new_scope, &name, named(value, &name));
+                self.bind_let(scope, new_scope, pending, body)
+            },
+            Frame::LetStar { new_scope, name, pending, body } => {
+                self.heap.set_local(new_scope, &name, named(value, &name));
+                self.bind_let_star(new_scope, pending, body)
+            },
+            Frame::Define { scope, name } => {
+                self.define(scope, &name, named(value, &name))?;
+                Ok(Control::Return(unspecified()))
+            },
+            Frame::Module { scope, name, exports } => {
+                if let Some(export) = exports.iter().find(|export| self.heap.get(scope, export).is_none()) {
+                    return Err(Error::new("module does not define what it exports",
+                                          vec![name_atom(&name), Atom::Identifier(export.clone())]))
+                }
+                self.modules.define(name, Module { scope, exports });
+                Ok(Control::Return(unspecified()))
+            },
+            Frame::Import { scope, module, pending } => {
+                if self.modules.get(&module).is_none() {
+                    return Err(Error::new("module file does not define the module", vec![name_atom(&module)]))
+                }
+                self.next_import(scope, pending)
+            },
+            Frame::Set { scope, name, site } => {
+                if !self.heap.set_inherited(scope, &name, value) {
+                    return Err(Error::located("attempt to set! undefined value", vec![Atom::Identifier(name)], site))
+                }
+                Ok(Control::Return(unspecified()))
+            },
+            Frame::And { scope, pending } => {
+                match value {
+                    Atom::Identifier(ref x) if x == "#f" => Ok(Control::Return(lithp_false())),
+                    Atom::Identifier(ref x) if x == "#t" => self.next_and(scope, pending),
+                    _ => Err(Error::from("arguments to and must be booleans"))
+                }
+            },
+            Frame::Or { scope, pending } => {
+                match value {
+                    Atom::Identifier(ref x) if x == "#t" => Ok(Control::Return(lithp_true())),
+                    Atom::Identifier(ref x) if x == "#f" => self.next_or(scope, pending),
+                    _ => Err(Error::from("arguments to and must be booleans"))
+                }
+            },
+            Frame::Cond { scope, body, pending } => {
+                if value == lithp_true() {
+                    Ok(Control::Eval(scope, body))
+                } else {
+                    self.next_cond(scope, pending)
+                }
+            },
+            Frame::DoStep { scope, do_loop, name, pending, mut values } => {
+                values.push((name, value));
+                self.next_do_step(scope, do_loop, pending, values)
+            },
+            Frame::DoTest { loop_scope, do_loop } => {
+                if value == lithp_true() {
+                    if do_loop.result.is_empty() {
+                        return Ok(Control::Return(unspecified()))
+                    }
+                    return self.eval_atoms(loop_scope, do_loop.result.clone())
+                }
+                if do_loop.commands.is_empty() {
+                    return self.start_do_steps(loop_scope, do_loop)
+                }
+                let commands = do_loop.commands.clone();
+                self.stack.push(Frame::DoCommands { loop_scope, do_loop });
+                self.eval_atoms(loop_scope, commands)
+            },
+            Frame::DoCommands { loop_scope, do_loop } => self.start_do_steps(loop_scope, do_loop),
+            Frame::WindBefore { before, thunk, after } => {
+                self.winders.push(Rc::new(Winder { before, after }));
+                self.stack.push(Frame::WindBody);
+                self.apply(thunk, vec![])
+            },
+            Frame::WindBody => {
+                let winder = self.winders.pop().expect("unbalanced dynamic-wind");
+                self.stack.push(Frame::WindAfter { value });
+                self.apply(winder.after.clone(), vec![])
+            },
+            Frame::WindAfter { value } => Ok(Control::Return(value)),
+            Frame::Rewind { steps, target, value } => self.rewind(steps, target, value),
+            Frame::Handler { .. } | Frame::Guard { .. } => Ok(Control::Return(value)),
+            Frame::Handled { handler_index, payload, continuable } => {
+                if continuable {
+                    return Ok(Control::Return(value))
+                }
+                self.stack.push(Frame::Handled { handler_index, payload: payload.clone(), continuable });
+                Err(Error::new("handler returned from non-continuable raise", vec![payload]))
+            },
+            Frame::Caught { scope, var, mut clauses } => {
+                let catch_scope = self.heap.new_child_scope(scope);
+                self.heap.set_local(catch_scope, &var, value.clone());
+                clauses.reverse();
+                self.next_catch(catch_scope, clauses, value)
+            },
+            Frame::Catch { scope, body, pending, payload } => {
+                if value == lithp_true() {
+                    self.eval_atoms(scope, body)
+                } else {
+                    self.next_catch(scope, pending, payload)
+                }
+            },
+            Frame::Map { func, pending, mut results } => {
+                if let Some(ref mut results) = results {
+                    results.push(value);
+                }
+                self.next_map(func, pending, results)
+            },
+            Frame::Filter { pred, current, pending, mut kept } => {
+                if value == lithp_true() {
+                    kept.push(current);
+                }
+                self.next_filter(pred, pending, kept)
+            },
+            Frame::Fold { func, pending, acc_first } => self.next_fold(func, pending, value, acc_first),
+            Frame::Find { compare, key, current, pending, assoc } => {
+                if value == lithp_true() {
+                    return Ok(Control::Return(found(current, pending, assoc)))
+                }
+                self.next_find(compare, key, pending, assoc)
+            },
+            Frame::Output { port } => {
+                let output = match *port.borrow() {
+                    Port::StringOutput(ref output) => Rc::from(output.as_str()),
+                    _ => Rc::from("")
+                };
+                Ok(Control::Return(Atom::String(output)))
+            },
+            Frame::ClosePort { port } => {
+                port.borrow_mut().close()?;
+                Ok(Control::Return(value))
+            },
+            Frame::Sort { mut sort } => {
+                // Taking from the right run only when it is strictly less
+                // keeps equal elements in their original order.
+                let head = if value == lithp_true() { sort.right.pop() } else { sort.left.pop() };
+                sort.out.extend(head);
+                self.next_merge(sort)
+            }
+        }
+    }
+
+    fn eval_let(&mut self, scope: ScopeRef, cdr: &[Atom]) -> StepResult {
+        if let Some((Atom::Identifier(name), cdr)) = cdr.split_first() {
+            return self.eval_named_let(scope, name, cdr)
+        }
+        let (binding_list, expressions) = split_let_body(cdr)?;
+        let new_scope = self.heap.new_child_scope(scope);
+        let mut bindings = extract_bindings(binding_list.clone())?;
+        bindings.reverse();
+        self.bind_let(scope, new_scope, bindings, expressions.to_vec())
+    }
+
+    fn bind_let(&mut self, scope: ScopeRef, new_scope: ScopeRef,
+                mut pending: Vec<(String, Atom)>, body: Vec<Atom>) -> StepResult {
+        match pending.pop() {
+            Some((name, expression)) => {
+                self.stack.push(Frame::Let { scope, new_scope, name, pending, body });
+                Ok(Control::Eval(scope, expression))
+            },
+            None => self.eval_atoms(new_scope, body)
+        }
+    }
+
+    fn eval_let_star(&mut self, scope: ScopeRef, cdr: &[Atom]) -> StepResult {
+        let (binding_list, expressions) = split_let_body(cdr)?;
+        let new_scope = self.heap.new_child_scope(scope);
+        let mut bindings = extract_bindings(binding_list.clone())?;
+        bindings.reverse();
+        self.bind_let_star(new_scope, bindings, expressions.to_vec())
+    }
+
+    fn bind_let_star(&mut self, new_scope: ScopeRef,
+                     mut pending: Vec<(String, Atom)>, body: Vec<Atom>) -> StepResult {
+        match pending.pop() {
+            Some((name, expression)) => {
+                self.stack.push(Frame::LetStar { new_scope, name, pending, body });
+                Ok(Control::Eval(new_scope, expression))
+            },
+            None => self.eval_atoms(new_scope, body)
+        }
+    }
+
+    // (let name ((var init)...) body...) binds name to a procedure taking the
+    // vars and evaluating body, visible only within body, then calls it with
+    // the inits.
+    fn eval_named_let(&mut self, scope: ScopeRef, name: &str, cdr: &[Atom]) -> StepResult {
+        let (binding_list, expressions) = split_let_body(cdr)?;
+        let bindings = extract_bindings(binding_list.clone())?;
+        let loop_scope = self.heap.new_child_scope(scope);
+        let (parameters, mut pending): (Vec<String>, Vec<Atom>) = bindings.into_iter().unzip();
+        let procedure = Atom::Lambda(Closure {
+            name: Some(name.to_string()),
+            scope: loop_scope,
+            parameters: Rc::new(Parameters { required: parameters, ..Parameters::default() }),
+            body: Box::new(Atom::List(expressions.to_vec()))
+        });
+        self.heap.set_local(loop_scope, name, procedure.clone());
+
+        pending.reverse();
+        match pending.pop() {
+            Some(first) => {
+                let evaluated = vec![procedure];
+                self.stack.push(Frame::Args { scope, site: None, pending, evaluated });
+                Ok(Control::Eval(scope, first))
+            },
+            None => self.apply(procedure, vec![])
+        }
+    }
+
+    // Each init is evaluated in the new scope, so the bound procedures can
+    // refer to each other and to themselves.
+    fn eval_letrec(&mut self, scope: ScopeRef, cdr: &[Atom]) -> StepResult {
+        let (binding_list, expressions) = split_let_body(cdr)?;
+        let new_scope = self.heap.new_child_scope(scope);
+        let mut bindings = extract_bindings(binding_list.clone())?;
+        bindings.reverse();
+        self.bind_let(new_scope, new_scope, bindings, expressions.to_vec())
+    }
+
+    // (do ((var init step)...) (test result...) command...) binds each var to
+    // its init, then until test holds runs the commands and rebinds each var
+    // to its step in a fresh scope. Vars without a step keep their value.
+    fn eval_do(&mut self, scope: ScopeRef, cdr: &[Atom]) -> StepResult {
+        let (specs, test_clause, commands) = match cdr {
+            [Atom::List(specs), Atom::List(test_clause), commands @ ..] if !test_clause.is_empty() => {
+                (specs, test_clause, commands)
+            },
+            _ => return Err(Error::from("invalid do"))
+        };
+        let mut inits = Vec::with_capacity(specs.len());
+        let mut steps = Vec::with_capacity(specs.len());
+        for spec in specs {
+            match *spec {
+                Atom::List(ref spec) if spec.len() == 2 || spec.len() == 3 => {
+                    let name = match spec[0] {
+                        Atom::Identifier(ref name) => name.clone(),
+                        _ => return Err(Error::from("do variables must be identifiers"))
+                    };
+                    if let Some(step) = spec.get(2) {
+                        steps.push((name.clone(), step.clone()));
+                    }
+                    inits.push((name, spec[1].clone()));
+                },
+                _ => return Err(Error::new("invalid do variable", vec![spec.clone()]))
+            }
+        }
+        let do_loop = Rc::new(DoLoop {
+            scope,
+            steps,
+            test: test_clause[0].clone(),
+            result: test_clause[1..].to_vec(),
+            commands: commands.to_vec()
+        });
+        inits.reverse();
+        self.next_do_step(scope, do_loop, inits, vec![])
+    }
+
+    fn start_do_steps(&mut self, loop_scope: ScopeRef, do_loop: Rc<DoLoop>) -> StepResult {
+        self.check_deadline()?;
+        let mut steps = do_loop.steps.clone();
+        steps.reverse();
+        self.next_do_step(loop_scope, do_loop, steps, vec![])
+    }
+
+    fn next_do_step(&mut self, scope: ScopeRef, do_loop: Rc<DoLoop>,
+                    mut pending: Vec<(String, Atom)>, values: Vec<(String, Atom)>) -> StepResult {
+        match pending.pop() {
+            Some((name, expression)) => {
+                self.stack.push(Frame::DoStep { scope, do_loop, name, pending, values });
+                Ok(Control::Eval(scope, expression))
+            },
+            None => {
+                let loop_scope = self.heap.new_child_scope(do_loop.scope);
+                if scope != do_loop.scope {
+                    for name in self.heap.scope(scope).names() {
+                        let value = self.heap.scope(scope).get_local(&name).unwrap();
+                        self.heap.set_local(loop_scope, &name, value);
+                    }
+                }
+                for (name, value) in values {
+                    self.heap.set_local(loop_scope, &name, value);
+                }
+                self.stack.push(Frame::DoTest { loop_scope, do_loop: do_loop.clone() });
+                Ok(Control::Eval(loop_scope, do_loop.test.clone()))
+            }
+        }
+    }
+
+    fn eval_define(&mut self, scope: ScopeRef, cdr: &[Atom]) -> StepResult {
+        if cdr.len() != 2 { return Err(Error::from("wrong number of arguments for define")) }
+        match cdr[0] {
+            Atom::Identifier(ref name) => {
+                self.stack.push(Frame::Define { scope, name: name.clone() });
+                Ok(Control::Eval(scope, cdr[1].clone()))
+            },
+            _ => Err(Error::from("first param of define must be an identifier"))
+        }
+    }
+
+    // (define-module (name...) (export name...) body...) evaluates the body in
+    // a scope of its own, whose exported bindings can then be imported.
+    fn eval_define_module(&mut self, cdr: &[Atom]) -> StepResult {
+        let (name, exports, body) = match cdr.split_first() {
+            Some((name, rest)) => match rest.split_first() {
+                Some((Atom::List(export), body)) if export.first() == Some(&Atom::Identifier("export".to_string())) => {
+                    (module_name(name)?, extract!(Atom::Identifier, &export[1..]), body)
+                },
+                _ => return Err(Error::from("define-module must be followed by a name and an export list"))
+            },
+            None => return Err(Error::from("define-module must be followed by a name and an export list"))
+        };
+        let scope = self.heap.new_child_scope(self.root_scope);
+        self.stack.push(Frame::Module { scope, name, exports });
+        self.eval_forms(scope, body.to_vec())
+    }
+
+    // (import set...) binds the exports of modules in scope, loading the file
+    // that defines each module the first time it is imported.
+    fn eval_import(&mut self, scope: ScopeRef, cdr: &[Atom]) -> StepResult {
+        let mut sets = cdr.iter().map(ImportSet::parse).collect::<Result<Vec<_>, _>>()?;
+        sets.reverse();
+        self.next_import(scope, sets)
+    }
+
+    fn next_import(&mut self, scope: ScopeRef, mut pending: Vec<ImportSet>) -> StepResult {
+        while let Some(set) = pending.pop() {
+            let module = match self.modules.get(set.module()) {
+                Some(module) => module,
+                None => {
+                    let name = set.module().to_vec();
+                    let loading = self.stack.iter().any(|frame| {
+                        matches!(*frame, Frame::Import { ref module, .. } if *module == name)
+                    });
+                    if loading {
+                        return Err(Error::new("circular import", vec![name_atom(&name)]))
+                    }
+                    if !self.options.capabilities.contains(&Capability::Filesystem) {
+                        return Err(Error::new("loading a module requires the filesystem capability",
+                                              vec![name_atom(&name)]))
+                    }
+                    let io = &self.io;
+                    let path = match self.modules.find(&module_path(&name), |path| io.exists(path)) {
+                        Some(path) => path,
+                        None => return Err(Error::new("module not found", vec![name_atom(&name)]))
+                    };
+                    let forms = self.load_file(&path)?;
+                    pending.push(set);
+                    self.stack.push(Frame::Import { scope, module: name, pending });
+                    return self.eval_forms(self.root_scope, forms)
+                }
+            };
+            let mut bindings = Vec::new();
+            for (to, from) in set.names(&module.exports)? {
+                bindings.push((to, self.heap.get(module.scope, &from).unwrap_or_else(unspecified)));
+            }
+            for (name, value) in bindings {
+                self.define(scope, &name, value)?;
+            }
+        }
+        Ok(Control::Return(unspecified()))
+    }
+
+    // Binds a name that is defined or imported. Pure mode checks forms for
+    // redefinitions before they run, but can't see what a module that isn't
+    // loaded yet exports, so it checks again here.
+    fn define(&mut self, scope: ScopeRef, name: &str, value: Atom) -> Result<(), Error> {
+        if self.options.pure && self.heap.scope(scope).has_local(name) {
+            return Err(Error::new("redefinition is not allowed in pure mode", vec![Atom::Identifier(name.to_string())]))
+        }
+        self.heap.set_local(scope, name, value);
+        Ok(())
+    }
+
+    // Evaluates the forms in a file at the top level, as if they were part of
+    // the program.
+    fn load(&mut self, args: Vec<Atom>) -> StepResult {
+        let io = &self.io;
+        let path = match args.as_slice() {
+            [Atom::String(path)] => self.modules.find(Path::new(&**path), |path| io.exists(path))
+                .ok_or_else(|| Error::new("file not found", args.clone()))?,
+            _ => return Err(Error::from("load requires a file name"))
+        };
+        let forms = self.load_file(&path)?;
+        self.eval_forms(self.root_scope, forms)
+    }
+
+    // Reads every form in a file, which must be free of syntax errors, and in
+    // pure mode pass its checks.
+    fn load_file(&mut self, path: &Path) -> Result<Vec<Atom>, Error> {
+        let file_name = || Atom::String(Rc::from(path.display().to_string()));
+        let mut source = String::new();
+        self.io.open_input(path)
+            .and_then(|mut input| input.read_to_string(&mut source))
+            .map_err(|error| Error::new(&format!("cannot read file: {}", error), vec![file_name()]))?;
+        let parsed = parse_program(&source, self.options.max_depth);
+        if let Some(diagnostic) = parsed.diagnostics.first() {
+            return Err(Error::new(&format!("syntax error at {}", diagnostic), vec![file_name()]))
+        }
+        let forms = located(parsed, &path.display().to_string());
+        self.check(&forms)?;
+        Ok(forms)
+    }
+
+    // Like `eval_atoms`, except that there may be nothing to evaluate.
+    fn eval_forms(&mut self, scope: ScopeRef, forms: Vec<Atom>) -> StepResult {
+        if forms.is_empty() {
+            return Ok(Control::Return(unspecified()))
+        }
+        self.eval_atoms(scope, forms)
+    }
+
+    // (set! name value), written at `site` if it was read from source.
+    fn eval_set(&mut self, scope: ScopeRef, cdr: &[Atom], site: Option<Rc<Location>>) -> StepResult {
+        if cdr.len() != 2 { return Err(Error::from("wrong number of arguments for set! "))}
+        match cdr[0] {
+            Atom::Identifier(ref name) => {
+                if self.heap.get(scope, name).is_none() {
+                    return Err(Error::located("attempt to set! undefined value", vec![cdr[0].clone()], site))
+                }
+                self.stack.push(Frame::Set { scope, name: name.clone(), site });
+                Ok(Control::Eval(scope, cdr[1].clone()))
+            },
+            _ => Err(Error::from("first parameter of set! must be an identifier"))
+        }
+    }
+
+    fn eval_lambda(&mut self, scope: ScopeRef, cdr: &[Atom]) -> StepResult {
+        match cdr.split_first() {
+            Some((params, body)) if !body.is_empty() => {
+                let parameters = parse_parameters(params)?;
+                Ok(Control::Return(Atom::Lambda(Closure{
+                    name: None,
+                    scope: self.heap.new_child_scope(scope),
+                    parameters: Rc::new(parameters),
+                    body: Box::new(Atom::List(body.to_vec()))
+                })))
+            },
+            _ => Err(Error::from("invalid lambda"))
+        }
+    }
+
+    fn eval_and(&mut self, scope: ScopeRef, args: &[Atom]) -> StepResult {
+        if args.is_empty() { return Err(Error::from("arguments to and may not be empty")) }
+        let mut pending = args.to_vec();
+        pending.reverse();
+        self.next_and(scope, pending)
+    }
+
+    fn next_and(&mut self, scope: ScopeRef, mut pending: Vec<Atom>) -> StepResult {
+        match pending.pop() {
+            Some(arg) => {
+                self.stack.push(Frame::And { scope, pending });
+                Ok(Control::Eval(scope, arg))
+            },
+            None => Ok(Control::Return(lithp_true()))
+        }
+    }
+
+    fn eval_cond(&mut self, scope: ScopeRef, args: &[Atom]) -> StepResult {
+        let mut pending = args.to_vec();
+        pending.reverse();
+        self.next_cond(scope, pending)
+    }
+
+    fn next_cond(&mut self, scope: ScopeRef, mut pending: Vec<Atom>) -> StepResult {
+        match pending.pop() {
+            Some(Atom::List(mut items)) if items.len() == 2 => {
+                let body = items.pop().unwrap();
+                let condition = items.pop().unwrap();
+                self.stack.push(Frame::Cond { scope, body, pending });
+                Ok(Control::Eval(scope, condition))
+            },
+            Some(_) => Err(Error::from("cond elements must be pairs")),
+            None => Ok(Control::Return(lithp_false()))
+        }
+    }
+
+    fn eval_or(&mut self, scope: ScopeRef, args: &[Atom]) -> StepResult {
+        if args.is_empty() { return Err(Error::from("arguments to and may not be empty")) }
+        let mut pending = args.to_vec();
+        pending.reverse();
+        self.next_or(scope, pending)
+    }
+
+    fn next_or(&mut self, scope: ScopeRef, mut pending: Vec<Atom>) -> StepResult {
+        match pending.pop() {
+            Some(arg) => {
+                self.stack.push(Frame::Or { scope, pending });
+                Ok(Control::Eval(scope, arg))
+            },
+            None => Ok(Control::Return(lithp_false()))
+        }
+    }
+
+    // (guard (var clause...) body...), where each clause is (test body...)
+    // or (else body...). The first clause whose test holds handles the
+    // raised object, bound to var. If none do it is raised again.
+    fn eval_guard(&mut self, scope: ScopeRef, cdr: &[Atom]) -> StepResult {
+        let (spec, body) = match cdr.split_first() {
+            Some((Atom::List(spec), body)) if !body.is_empty() => (spec, body),
+            _ => return Err(Error::from("invalid guard"))
+        };
+        let (var, clauses) = match spec.split_first() {
+            Some((Atom::Identifier(var), clauses)) => (var.clone(), clauses.to_vec()),
+            _ => return Err(Error::from("guard must name the raised object"))
+        };
+        self.stack.push(Frame::Guard { scope, var, clauses, winders: self.winders.clone() });
+        self.eval_atoms(scope, body.to_vec())
+    }
+
+    fn next_catch(&mut self, scope: ScopeRef, mut pending: Vec<Atom>, payload: Atom) -> StepResult {
+        match pending.pop() {
+            Some(Atom::List(mut clause)) if clause.len() >= 2 => {
+                let body = clause.split_off(1);
+                match clause.pop().unwrap() {
+                    Atom::Identifier(ref x) if x == "else" => self.eval_atoms(scope, body),
+                    test => {
+                        self.stack.push(Frame::Catch { scope, body, pending, payload });
+                        Ok(Control::Eval(scope, test))
+                    }
+                }
+            },
+            Some(_) => Err(Error::from("guard clauses must have a test and a body")),
+            None => Err(Error::raise(payload))
+        }
+    }
+
+    fn try_get(&self, scope: ScopeRef, name: &str) -> Result<Atom, Error> {
+        match self.heap.get(scope, name) {
+            Some(atom) => Ok(atom),
+            None => match BUILT_INS.iter().find(|built_in| built_in.0 == name) {
+                Some(&(name, capability, _)) if self.allows(capability) => Ok(Atom::Builtin(name)),
+                _ => Err(Error::new("unknown identifier", vec![Atom::Identifier(name.to_string())]))
+            }
+        }
+    }
+
+    fn apply(&mut self, func: Atom, args: Vec<Atom>) -> StepResult {
+        self.apply_at(func, args, None)
+    }
+
+    // Calls `func` from `site`, where the call was written if it was read
+    // from source.
+    fn apply_at(&mut self, func: Atom, args: Vec<Atom>, site: Option<Rc<Location>>) -> StepResult {
+        self.check_deadline()?;
+        match func {
+            Atom::Builtin(name) if self.profiler.is_some() => {
+                let depth = self.profiler.as_ref().map_or(0, Profiler::depth);
+                let allocated = self.heap.stats().allocated;
+                let start = Instant::now();
+                let result = self.apply_builtin(name, args);
+                let elapsed = start.elapsed();
+                let allocated = self.heap.stats().allocated - allocated;
+                if let Some(ref mut profiler) = self.profiler {
+                    profiler.built_in(name, depth, elapsed, allocated);
+                }
+                result
+            },
+            Atom::Builtin(name) => self.apply_builtin(name, args),
+            Atom::Lambda(closure) => self.apply_closure(&closure, args, site),
+            Atom::Continuation(continuation) => self.throw(continuation, args),
+            _ => Err(Error::from("cannot apply value of given type"))
+        }
+    }
+
+    // Tells the profiler, if there is one, that the calls whose frames have
+    // gone from the stack have returned.
+    fn profile_returns(&mut self) {
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.leave(self.stack.len(), self.heap.stats().allocated);
+        }
+    }
+
+    fn apply_builtin(&mut self, name: &'static str, args: Vec<Atom>) -> StepResult {
+        let cells = allocation(name, &args);
+        if cells > 0 {
+            self.reserve(cells, &args)?;
+        }
+        match BUILT_INS.iter().find(|built_in| built_in.0 == name) {
+            // A built in may have been got at other than by name, as in a
+            // value handed in by an embedder, so it is checked again here.
+            Some(&(_, capability, _)) if !self.allows(capability) => {
+                Err(Error::new(&format!("calling {} requires the {} capability", name, capability.name()), vec![]))
+            },
+            Some(&(_, _, built_in)) => built_in(self, name, args),
+            None => Err(Error::new("unknown function", vec![Atom::Identifier(name.to_string())]))
+        }
     }
-    eval_atoms(root_scope, atom)
-}
 
-fn eval_atoms(scope: ScopeRef<Atom>, atom: Atom) -> Result<Atom, &'static str> {
-    let result = match atom {
-        Atom::List(atoms) => {
-            let mut evaluated = Vec::with_capacity(atoms.len());
-            for atom in atoms {
-                evaluated.push(try!(eval_atom(scope.clone(), atom)));
-            }
-            match evaluated.last() {
-                Some(value) => Ok(value.clone()),
-                None => Err("eval atoms on empty list")
+    fn apply_closure(&mut self, closure: &Closure, args: Vec<Atom>, site: Option<Rc<Location>>) -> StepResult {
+        // Only a procedure held outside the interpreter without being rooted
+        // can outlive its scope.
+        if !self.heap.is_live(closure.scope) {
+            return Err(Error::new("call of a procedure that was collected", vec![Atom::Lambda(closure.clone())]))
+        }
+        // The closure being called in tail position takes the place of its
+        // caller, which has nothing left to do.
+        if let Some(Frame::Call { .. }) = self.stack.last() {
+            self.stack.pop();
+        }
+        if let Some(ref mut profiler) = self.profiler {
+            let allocated = self.heap.stats().allocated;
+            profiler.leave(self.stack.len(), allocated);
+            profiler.enter(procedure(closure), self.stack.len(), allocated);
+        }
+        // The arguments are shared with the call's frame rather than copied,
+        // as they may be long lists.
+        let args: Rc<[Atom]> = Rc::from(args);
+        self.stack.push(Frame::Call { call: Call { name: closure.name.clone(), site, args: args.clone() } });
+        if self.hooks.is_some() {
+            self.hook(|hooks, _| hooks.call(closure));
+        }
+        let parameters = &closure.parameters;
+        let arity_error = || {
+            Error::new("invalid closure arity", vec![Atom::Integer(args.len() as i64)])
+        };
+        if args.len() < parameters.required.len() {
+            return Err(arity_error())
+        }
+
+        let call_scope = self.heap.new_child_scope(closure.scope);
+        let mut args = args.iter();
+        for parameter in &parameters.required {
+            self.heap.set_local(call_scope, parameter, args.next().unwrap().clone());
+        }
+
+        // Parameters that weren't passed are bound to their defaults in order
+        // once everything passed has been bound, as if by let*.
+        let mut defaults = Vec::new();
+        for (parameter, default) in &parameters.optional {
+            let keys_started = !parameters.keys.is_empty() &&
+                args.as_slice().first().is_some_and(is_keyword);
+            match args.as_slice().first() {
+                Some(arg) if !keys_started => {
+                    self.heap.set_local(call_scope, parameter, arg.clone());
+                    args.next();
+                },
+                _ => defaults.push((parameter.clone(), default_value(default)))
             }
-        },
-        _ => Err("eval_atoms must be called with atom list")
-    };
-    result
-}
-
-fn eval_atom(scope: ScopeRef<Atom>, atom: Atom) -> Result<Atom, &'static str> {
-    let original = atom.clone();
-    let result = match atom {
-        Atom::Quoted(value) => { println!("eval( ' ) -> '");  Ok(*value) },
-        Atom::Integer(_) | Atom::Lambda(_) => Ok(atom),
-        Atom::Identifier(ref name) => try_get(scope, name),
-        Atom::List(atoms) => {
-            match atoms.split_first() {
-                // Macros / syntax rules, which have special evaluation
-                Some((&Atom::Identifier(ref x), cdr)) if x == "let" => eval_let(scope, cdr),
-                Some((&Atom::Identifier(ref x), cdr)) if x == "let*" => eval_let_star(scope, cdr),
-                Some((&Atom::Identifier(ref x), cdr)) if x == "define" => eval_define(scope, cdr),
-                Some((&Atom::Identifier(ref x), cdr)) if x == "set!" => eval_set(scope, cdr),
-                Some((&Atom::Identifier(ref x), cdr)) if x == "lambda" => eval_lambda(scope, cdr),
-                // For short circuiting
-                Some((&Atom::Identifier(ref x), cdr)) if x == "and" => eval_and(scope, cdr),
-                Some((&Atom::Identifier(ref x), cdr)) if x == "or" => eval_or(scope, cdr),
-                Some((&Atom::Identifier(ref x), cdr)) if x == "cond" => eval_cond(scope, cdr),
-                _ => {
-                    let mut evaluated = Vec::with_capacity(atoms.len());
-                    for atom in atoms.clone() {
-                        evaluated.push(try!(eval_atom(scope.clone(), atom)));
-                    }
-                    match evaluated.split_first() {
-                        Some((car, cdr)) => apply(&car, &cdr),
-                        None => Err("invalid empty expression")
-                    }
+        }
+
+        let remaining = args.as_slice();
+        let mut passed_keys = Vec::new();
+        if !parameters.keys.is_empty() {
+            let mut pairs = remaining.iter();
+            while let Some(arg) = pairs.next() {
+                let key = match *arg {
+                    Atom::Identifier(ref name) if is_keyword(arg) => &name[2..],
+                    _ if parameters.rest.is_some() => continue,
+                    _ => return Err(Error::new("expected a keyword argument", vec![arg.clone()]))
+                };
+                let value = match pairs.next() {
+                    Some(value) => value.clone(),
+                    None => return Err(Error::new("missing value for keyword argument", vec![arg.clone()]))
+                };
+                if parameters.keys.iter().any(|(name, _)| name == key) {
+                    self.heap.set_local(call_scope, key, value);
+                    passed_keys.push(key);
+                } else if parameters.rest.is_none() {
+                    return Err(Error::new("unknown keyword argument", vec![arg.clone()]))
                 }
             }
-        },
-    };
-    if let &Ok(ref evaluated) = &result {
-        println!("eval( {} ) -> {}", original, evaluated)
+        }
+        for (parameter, default) in &parameters.keys {
+            if !passed_keys.contains(&parameter.as_str()) {
+                defaults.push((parameter.clone(), default_value(default)));
+            }
+        }
+
+        match parameters.rest {
+            Some(ref rest) => {
+                let rest_list = self.within_depth(Atom::List(remaining.to_vec()))?;
+                self.heap.set_local(call_scope, rest, rest_list)
+            },
+            None if parameters.keys.is_empty() && !remaining.is_empty() => return Err(arity_error()),
+            None => ()
+        }
+
+        let body = match *closure.body {
+            Atom::List(ref body) => body.clone(),
+            _ => return Err(Error::from("eval_atoms must be called with atom list"))
+        };
+        defaults.reverse();
+        self.bind_let_star(call_scope, defaults, body)
     }
-    result
-}
 
-fn eval_let(scope: ScopeRef<Atom>, cdr: &[Atom]) -> Result<Atom, &'static str> {
-    println!("eval( let ) -> let");
-    let (binding_list, expressions) = try!(split_let_body(cdr));
-    let new_scope = new_child_scope(&scope);
-    let bindings = try!(extract_bindings(binding_list.clone()));
-    for (name, expression) in bindings {
-        let value = try!(eval_atom(scope.clone(), expression));
-        new_scope.borrow_mut().set_local(&name, value);
+    // (apply f a b (c d)) calls f with the arguments a, b, c and d.
+    fn apply_spread(&mut self, mut args: Vec<Atom>) -> StepResult {
+        if args.len() < 2 { return Err(Error::from("invalid arity for apply")) }
+        let spread = match args.pop().unwrap() {
+            Atom::List(spread) => spread,
+            _ => return Err(Error::from("last argument to apply must be a list"))
+        };
+        let func = args.remove(0);
+        args.extend(spread);
+        self.apply(func, args)
     }
-    
-    let result = eval_atoms(new_scope, Atom::List(expressions.to_vec()));
-    result
-}
 
-fn eval_let_star(scope: ScopeRef<Atom>, cdr: &[Atom]) -> Result<Atom, &'static str> {
-    println!("eval( let* ) -> let*");
-    let (binding_list, expressions) = try!(split_let_body(cdr));
-    let new_scope = new_child_scope(&scope);
-    let bindings = try!(extract_bindings(binding_list.clone()));
-    for (name, expression) in bindings {
-        let value = try!(eval_atom(new_scope.clone(), expression));
-        new_scope.borrow_mut().set_local(&name, value);
+    fn call_cc(&mut self, mut args: Vec<Atom>) -> StepResult {
+        if args.len() != 1 { return Err(Error::from("invalid arity for call/cc")) }
+        let continuation = Continuation {
+            stack: self.stack.clone(),
+            winders: self.winders.clone()
+        };
+        let receiver = args.pop().unwrap();
+        self.apply(receiver, vec![Atom::Continuation(Rc::new(continuation))])
     }
 
-    let result = eval_atoms(new_scope, Atom::List(expressions.to_vec()));
-    result   
-}
+    fn dynamic_wind(&mut self, mut args: Vec<Atom>) -> StepResult {
+        if args.len() != 3 { return Err(Error::from("invalid arity for dynamic-wind")) }
+        let after = args.pop().unwrap();
+        let thunk = args.pop().unwrap();
+        let before = args.pop().unwrap();
+        self.stack.push(Frame::WindBefore { before: before.clone(), thunk, after });
+        self.apply(before, vec![])
+    }
 
-fn eval_define(scope: ScopeRef<Atom>, cdr: &[Atom]) -> Result<Atom, &'static str> {
-    println!("eval( define ) -> define");
-    if cdr.len() != 2 { return Err("wrong number of arguments for define") }
-    match cdr[0] {
-        Atom::Identifier(ref name) => {
-            let evaluated = try!(eval_atom(scope.clone(), cdr[1].clone()));
-            scope.borrow_mut().set_local(&name, evaluated);
-            Ok(Atom::Identifier("".to_string()))
-        },
-        _ => Err("first param of define must be an identifier")
+    // Jumps to `target`, first running the after thunks of every
+    // `dynamic-wind` being left and then the before thunks of every one
+    // being re-entered.
+    fn throw(&mut self, target: Rc<Continuation>, mut args: Vec<Atom>) -> StepResult {
+        if args.len() != 1 { return Err(Error::from("continuations take exactly one value")) }
+        let value = args.pop().unwrap();
+
+        let common = self.winders.iter().zip(&target.winders)
+            .take_while(|&(a, b)| Rc::ptr_eq(a, b))
+            .count();
+        let mut steps = Vec::new();
+        for depth in (common..self.winders.len()).rev() {
+            steps.push((self.winders[depth].after.clone(), self.winders[..depth].to_vec()));
+        }
+        for depth in common..target.winders.len() {
+            steps.push((target.winders[depth].before.clone(), target.winders[..depth].to_vec()));
+        }
+        steps.reverse();
+        self.rewind(steps, target, value)
     }
-}
 
-fn eval_set(scope: ScopeRef<Atom>, cdr: &[Atom]) -> Result<Atom, &'static str> {
-    println!("eval( set! ) -> set!");
-    if cdr.len() != 2 { return Err("wrong number of arguments for set! ")}
-    match cdr[0] {
-        Atom::Identifier(ref name) => {
-            let existing = { scope.borrow().get(name) };
-            match existing {
-                Some(old_value) => {
-                    let evaluated = try!(eval_atom(scope.clone(), cdr[1].clone()));
-                    scope.borrow_mut().set_inherited(&name, evaluated);
-                    Ok(old_value)
-                },
-                None => Err("attempt to set! undefined value")
+    fn rewind(&mut self, mut steps: Vec<(Atom, Vec<Rc<Winder>>)>,
+              target: Rc<Continuation>, value: Atom) -> StepResult {
+        match steps.pop() {
+            Some((thunk, winders)) => {
+                self.winders = winders;
+                self.stack.push(Frame::Rewind { steps, target, value });
+                self.apply(thunk, vec![])
+            },
+            None => {
+                self.stack = target.stack.clone();
+                self.winders = target.winders.clone();
+                self.profile_returns();
+                Ok(Control::Return(value))
             }
-        },
-        _ => Err("first parameter of set! must be an identifier")
+        }
     }
-}
 
-macro_rules! extract {
-    ( $t:path, $cdr:expr ) => {{
-        let cdr = $cdr;
-        let mut result = Vec::with_capacity(cdr.len());
-        for atom in cdr {
-            match atom {
-                &$t(ref val) => result.push(val.clone()),
-                _ => return Err("invalid format")
+    fn with_exception_handler(&mut self, mut args: Vec<Atom>) -> StepResult {
+        if args.len() != 2 { return Err(Error::from("invalid arity for with-exception-handler")) }
+        let thunk = args.pop().unwrap();
+        let handler = args.pop().unwrap();
+        self.stack.push(Frame::Handler { handler });
+        self.apply(thunk, vec![])
+    }
+
+    // (map f list...) calls f with the first element of each list, then the
+    // second, and so on until the shortest list runs out, returning a list of
+    // the results. `for-each` is the same, except that it returns nothing.
+    fn map(&mut self, args: Vec<Atom>, collect: bool) -> StepResult {
+        let usage = if collect { "map requires a procedure and lists" } else { "for-each requires a procedure and lists" };
+        let (func, lists) = match args.split_first() {
+            Some((func, lists)) if !lists.is_empty() => (func.clone(), extract!(Atom::List, lists)),
+            _ => return Err(Error::from(usage))
+        };
+        let count = lists.iter().map(Vec::len).min().unwrap_or(0);
+        let pending = (0..count).rev()
+            .map(|i| lists.iter().map(|list| list[i].clone()).collect())
+            .collect();
+        let results = if collect { Some(Vec::with_capacity(count)) } else { None };
+        self.next_map(func, pending, results)
+    }
+
+    fn next_map(&mut self, func: Atom, mut pending: Vec<Vec<Atom>>,
+                results: Option<Vec<Atom>>) -> StepResult {
+        match pending.pop() {
+            Some(args) => {
+                self.stack.push(Frame::Map { func: func.clone(), pending, results });
+                self.apply(func, args)
+            },
+            None => match results {
+                Some(results) => Ok(Control::Return(self.within_depth(Atom::List(results))?)),
+                None => Ok(Control::Return(unspecified()))
             }
         }
-        result
-    }}
-}
+    }
 
-fn eval_lambda(scope: ScopeRef<Atom>, cdr: &[Atom]) -> Result<Atom, &'static str> {
-    println!("eval( lambda ) -> lambda");
-    match cdr.split_first() {
-        Some((&Atom::List(ref params), ref body)) if !body.is_empty() => {
-            let param_names = extract!(Atom::Identifier, params);
-            Ok(Atom::Lambda(Closure{
-                scope: new_child_scope(&scope),
-                parameters: param_names,
-                body: Box::new(Atom::List(body.to_vec()))
-            }))
-        },
-        _ => Err("invalid lambda")
+    // (filter pred list) lists the elements of list that pred holds for.
+    fn filter(&mut self, args: Vec<Atom>) -> StepResult {
+        match args.as_slice() {
+            [pred, Atom::List(items)] => {
+                let mut pending = items.clone();
+                pending.reverse();
+                self.next_filter(pred.clone(), pending, Vec::new())
+            },
+            _ => Err(Error::from("filter requires a predicate and a list"))
+        }
     }
-}
 
-fn eval_and(scope: ScopeRef<Atom>, args: &[Atom]) -> BasicResult<Atom> {
-    println!("eval( and ) -> and");
-    if args.is_empty() { return Err("arguments to and may not be empty") }
-    for arg in args {
-        let evaluated = try!(eval_atom(scope.clone(), arg.clone()));
-        match evaluated {
-            Atom::Identifier(ref x) if x == "#f" => return Ok(lithp_false()),
-            Atom::Identifier(ref x) if x == "#t" => (),
-            _ => return Err("arguments to and must be booleans")
+    fn next_filter(&mut self, pred: Atom, mut pending: Vec<Atom>, kept: Vec<Atom>) -> StepResult {
+        match pending.pop() {
+            Some(current) => {
+                self.stack.push(Frame::Filter { pred: pred.clone(), current: current.clone(), pending, kept });
+                self.apply(pred, vec![current])
+            },
+            None => Ok(Control::Return(Atom::List(kept)))
         }
     }
-    Ok(lithp_true())
-}
 
-fn eval_cond(scope: ScopeRef<Atom>, args: &[Atom]) -> BasicResult<Atom> {
-    println!("eval( cond ) -> cond");
-    for arg in args {
-        match arg {
-            &Atom::List(ref items) if items.len() == 2 => {
-                let evaluated_condition = try!(eval_atom(scope.clone(),
-                                               items[0].clone()));
-                if evaluated_condition == lithp_true() {
-                    return eval_atom(scope.clone(), items[1].clone());
+    // (fold-left f init list) calls (f acc x) with each element from the
+    // left, and (fold-right f init list) calls (f x acc) with each from the
+    // right, where acc is init and then the result of the call before.
+    // (reduce f default list) calls (f x acc) from the left starting with the
+    // first element as acc, returning default if the list is empty.
+    fn fold(&mut self, name: &str, args: Vec<Atom>) -> StepResult {
+        let (func, init, mut items) = match args.as_slice() {
+            [func, init, Atom::List(items)] => (func.clone(), init.clone(), items.clone()),
+            _ => return Err(Error::from("folds require a procedure, an initial value and a list"))
+        };
+        let (acc, acc_first) = match name {
+            "fold-left" => (init, true),
+            "fold-right" => return self.next_fold(func, items, init, false),
+            _ if items.is_empty() => return Ok(Control::Return(init)),
+            _ => (items.remove(0), false)
+        };
+        items.reverse();
+        self.next_fold(func, items, acc, acc_first)
+    }
+
+    fn next_fold(&mut self, func: Atom, mut pending: Vec<Atom>, acc: Atom, acc_first: bool) -> StepResult {
+        match pending.pop() {
+            Some(next) => {
+                let args = if acc_first { vec![acc, next] } else { vec![next, acc] };
+                self.stack.push(Frame::Fold { func: func.clone(), pending, acc_first });
+                self.apply(func, args)
+            },
+            None => Ok(Control::Return(acc))
+        }
+    }
+
+    // (member x list) returns the first tail of list starting with x, and
+    // (assoc key alist) the first pair in alist whose car is key, or #f if
+    // there isn't one. Either compares with equal? unless given a procedure
+    // to compare with.
+    fn find(&mut self, args: Vec<Atom>, assoc: bool) -> StepResult {
+        let (key, items, compare) = match args.as_slice() {
+            [key, Atom::List(items)] => (key.clone(), items, None),
+            [key, Atom::List(items), compare] => (key.clone(), items, Some(compare.clone())),
+            _ if assoc => return Err(Error::from("assoc requires a key and a list of pairs")),
+            _ => return Err(Error::from("member requires an item and a list"))
+        };
+        let mut pending = items.clone();
+        pending.reverse();
+        match compare {
+            Some(compare) => self.next_find(compare, key, pending, assoc),
+            None => {
+                while let Some(current) = pending.pop() {
+                    if find_key(&current, assoc)? == key {
+                        return Ok(Control::Return(found(current, pending, assoc)))
+                    }
                 }
+                Ok(Control::Return(lithp_false()))
+            }
+        }
+    }
+
+    fn next_find(&mut self, compare: Atom, key: Atom, mut pending: Vec<Atom>, assoc: bool) -> StepResult {
+        match pending.pop() {
+            Some(current) => {
+                let args = vec![key.clone(), find_key(&current, assoc)?];
+                self.stack.push(Frame::Find { compare: compare.clone(), key, current, pending, assoc });
+                self.apply(compare, args)
             },
-            _ => return Err("cond elements must be pairs")
+            None => Ok(Control::Return(lithp_false()))
         }
     }
-    Ok(lithp_false())
-}
 
-fn eval_or(scope: ScopeRef<Atom>, args: &[Atom]) -> BasicResult<Atom> {
-    println!("eval( or ) -> or");
-    if args.is_empty() { return Err("arguments to and may not be empty") }
-    for arg in args {
-        let evaluated = try!(eval_atom(scope.clone(), arg.clone()));
-        match evaluated {
-            Atom::Identifier(ref x) if x == "#t" => return Ok(lithp_true()),
-            Atom::Identifier(ref x) if x == "#f" => (),
-            _ => return Err("arguments to and must be booleans")
+    // (sort list less?) sorts a list into the order given by less?, keeping
+    // elements that are neither less than the other in their original order.
+    fn sort(&mut self, args: Vec<Atom>) -> StepResult {
+        match args.as_slice() {
+            [Atom::List(items), less] => self.next_merge(Box::new(MergeSort {
+                less: less.clone(),
+                runs: items.iter().rev().map(|item| vec![item.clone()]).collect(),
+                merged: Vec::new(),
+                left: Vec::new(),
+                right: Vec::new(),
+                out: Vec::new()
+            })),
+            _ => Err(Error::from("sort requires a list and a procedure"))
         }
     }
-    Ok(lithp_false())
-}
 
-fn try_get(scope: ScopeRef<Atom>, name: &str) -> Result<Atom, &'static str> {
-    match scope.borrow().get(name) {
-        Some(atom) => Ok(atom),
-        None => {
-            if BUILT_INS.contains(&name) {
-                Ok(Atom::Identifier(name.to_string()))
-            } else {
-                println!("unknown identifier is {}", name);
-                Err("unknown identifier")
+    fn next_merge(&mut self, mut sort: Box<MergeSort>) -> StepResult {
+        loop {
+            if let (Some(left), Some(right)) = (sort.left.last(), sort.right.last()) {
+                let args = vec![right.clone(), left.clone()];
+                let less = sort.less.clone();
+                self.stack.push(Frame::Sort { sort });
+                return self.apply(less, args)
+            }
+            // Once either run is used up, the rest of the other follows.
+            if !sort.out.is_empty() {
+                let mut out = mem::take(&mut sort.out);
+                out.extend(sort.left.drain(..).rev());
+                out.extend(sort.right.drain(..).rev());
+                sort.merged.push(out);
+            }
+            match sort.runs.len() {
+                0 if sort.merged.len() <= 1 => {
+                    return Ok(Control::Return(Atom::List(sort.merged.pop().unwrap_or_default())))
+                },
+                0 => {
+                    sort.runs = mem::take(&mut sort.merged);
+                    sort.runs.reverse();
+                },
+                1 => {
+                    let run = sort.runs.pop().unwrap();
+                    sort.merged.push(run);
+                },
+                _ => {
+                    sort.left = sort.runs.pop().unwrap();
+                    sort.left.reverse();
+                    sort.right = sort.runs.pop().unwrap();
+                    sort.right.reverse();
+                }
             }
         }
     }
-}
 
-fn apply(func: &Atom, args: &[Atom]) -> Result<Atom, &'static str> {
-    match func {
-        &Atom::Identifier(ref name) => {
-            let name_ref: &str = name;
-            match name_ref {
-                "+" => math(0, &|a, &b| a + b, args),
-                "*" => math(1, &|a, &b| a * b, args),
-                "/" => math_first(&|a, &b| a / b, args),
-                "-" => math_first(&|a, &b| a - b, args),
-                "car" => car(args),
-                "cdr" => cdr(args),
-                "cons" => cons(args),
-                "list" => list(args),
-                ">" => gt(args),
-                "<" => lt(args),
-                "=" => eq(args),
-                "equal?" => equal(args),
-                "not" => not(args),
-                _ => Err("unknown function")
-            }
-        },
-        &Atom::Lambda(ref closure) => apply_closure(closure, args),
-        _ => Err("cannot apply value of given type")
+    // (read port) reads the next datum from an input port, or the console.
+    fn read(&mut self, args: &[Atom]) -> BasicResult<Atom> {
+        let datum = match args {
+            [] => self.input()?.read(),
+            [Atom::Port(port)] => port.borrow_mut().reader()?.read(),
+            _ => return Err("read takes an optional input port")
+        };
+        Ok(datum.map_err(|diagnostic| diagnostic.message)?.unwrap_or_else(eof_object))
+    }
+
+    // (read-line port) reads the rest of the current line as a string.
+    fn read_line(&mut self, args: &[Atom]) -> BasicResult<Atom> {
+        let line = match args {
+            [] => self.input()?.read_line(),
+            [Atom::Port(port)] => port.borrow_mut().reader()?.read_line(),
+            _ => return Err("read-line takes an optional input port")
+        };
+        let line = line.map_err(|diagnostic| diagnostic.message)?;
+        Ok(line.map_or_else(eof_object, |line| Atom::String(Rc::from(line))))
+    }
+
+    // (write x port) writes x so that `read` would read it back, and
+    // (display x port) writes it for people to read.
+    fn write(&mut self, args: &[Atom], human: bool) -> Result<Atom, Error> {
+        let (atom, port) = match args {
+            [atom] => (atom, None),
+            [atom, port] => (atom, Some(port)),
+            _ if human => return Err(Error::from("invalid arity for display")),
+            _ => return Err(Error::from("invalid arity for write"))
+        };
+        let text = if human { Displayed(atom).to_string() } else { atom.to_string() };
+        self.output(port, &text)?;
+        Ok(unspecified())
+    }
+
+    fn newline(&mut self, args: &[Atom]) -> Result<Atom, Error> {
+        if args.len() > 1 { return Err(Error::from("invalid arity for newline")) }
+        self.output(args.first(), "\n")?;
+        Ok(unspecified())
+    }
+
+    // Writes an atom broken across lines to fit the given width, or 80
+    // columns.
+    fn pp(&mut self, args: &[Atom]) -> Result<Atom, Error> {
+        let width = match args {
+            [_] => DEFAULT_WIDTH,
+            [_, Atom::Integer(width)] if *width > 0 => *width as usize,
+            [_, _] => return Err(Error::from("pp width must be a positive integer")),
+            _ => return Err(Error::from("invalid arity for pp"))
+        };
+        self.output(None, &format!("{}\n", pretty(&args[0], width)))?;
+        Ok(unspecified())
+    }
+
+    // Writes to the port given, or else to the current output: the string of
+    // the innermost `with-output-to-string` in effect, or the console.
+    fn output(&mut self, port: Option<&Atom>, text: &str) -> Result<(), Error> {
+        let port = match port {
+            Some(Atom::Port(port)) => Some(port.clone()),
+            Some(_) => return Err(Error::from("expected an output port")),
+            None => self.stack.iter().rev().find_map(|frame| match *frame {
+                Frame::Output { ref port } => Some(port.clone()),
+                _ => None
+            })
+        };
+        match port {
+            Some(port) => {
+                // What is written to a string is kept, so counts as allocated.
+                let string = matches!(*port.borrow(), Port::StringOutput(_));
+                if string {
+                    self.reserve(text.len(), &[])?;
+                }
+                port.borrow_mut().write(text).map_err(Error::from)
+            },
+            None => self.io.write(text).map_err(|_| Error::from("error writing output"))
+        }
+    }
+
+    fn open_file(&mut self, args: &[Atom], output: bool) -> Result<PortRef, Error> {
+        let path = match args {
+            [Atom::String(path)] => Path::new(&**path),
+            _ => return Err(Error::from("opening a file requires a file name"))
+        };
+        let port = if output {
+            self.io.open_output(path).map(Port::Output)
+        } else {
+            let max_depth = self.options.max_depth;
+            self.io.open_input(path).map(|input| Port::Input(Reader::new(input).max_depth(max_depth)))
+        };
+        port.map(Port::new)
+            .map_err(|error| Error::new(&format!("cannot open file: {}", error), args.to_vec()))
     }
-}
 
-fn apply_closure(closure: &Closure, args: &[Atom]) -> Result<Atom, &'static str> {
-    if closure.parameters.len() != args.len() { 
-        return Err("invalid closure arity")
+    // (with-output-to-string thunk) returns what thunk writes without a port.
+    fn with_output_to_string(&mut self, args: Vec<Atom>) -> StepResult {
+        let thunk = single(args)?;
+        self.stack.push(Frame::Output { port: Port::new(Port::StringOutput(String::new())) });
+        self.apply(thunk, vec![])
     }
-    
-    let call_scope = new_child_scope(&closure.scope);
-    for (i, arg) in args.iter().enumerate() {
-        call_scope.borrow_mut().set_local(&closure.parameters[i], arg.clone());
+
+    // (call-with-input-file name proc) calls proc with a port reading the
+    // file, closing it once proc returns.
+    fn call_with_input_file(&mut self, args: Vec<Atom>) -> StepResult {
+        let (path, procedure) = match args.as_slice() {
+            [path, procedure] => (path, procedure.clone()),
+            _ => return Err(Error::from("call-with-input-file requires a file name and a procedure"))
+        };
+        let port = self.open_file(slice::from_ref(path), false)?;
+        self.stack.push(Frame::ClosePort { port: port.clone() });
+        self.apply(procedure, vec![Atom::Port(port)])
+    }
+
+    fn file_exists(&mut self, args: &[Atom]) -> BasicResult<Atom> {
+        match args {
+            [Atom::String(path)] => Ok(lithp_bool(self.io.exists(Path::new(&**path)))),
+            _ => Err("file-exists? requires a file name")
+        }
+    }
+
+    fn input(&mut self) -> BasicResult<&mut Reader<Box<dyn BufRead>>> {
+        if self.input.is_none() {
+            let input = self.io.stdin().map_err(|_| "cannot read from the console")?;
+            self.input = Some(Reader::new(input).max_depth(self.options.max_depth));
+        }
+        Ok(self.input.as_mut().unwrap())
+    }
+
+    fn gc(&mut self, args: &[Atom]) -> BasicResult<Atom> {
+        if !args.is_empty() { return Err("invalid arity for gc") }
+        Ok(Atom::Integer(self.collect_garbage(None, &[]) as i64))
+    }
+
+    fn gc_stats(&mut self, args: &[Atom]) -> BasicResult<Atom> {
+        if !args.is_empty() { return Err("invalid arity for gc-stats") }
+        let stats = self.heap.stats();
+        let stat = |name: &str, value: usize| {
+            Atom::List(vec![Atom::Identifier(name.to_string()), Atom::Integer(value as i64)])
+        };
+        Ok(Atom::List(vec![
+            stat("collections", stats.collections),
+            stat("allocated", stats.allocated),
+            stat("freed", stats.freed),
+            stat("live", stats.live)
+        ]))
     }
-    return eval_atoms(call_scope, *closure.body.clone());
+}
+
+// What the profiler counts calls of `closure` as: its name, or where its body
+// starts if it has none.
+fn procedure(closure: &Closure) -> Procedure {
+    let location = match *closure.body {
+        Atom::List(ref body) => match body.first() {
+            Some(Atom::Form(location, _)) => Some(location.to_string()),
+            _ => None
+        },
+        _ => None
+    };
+    let name = match (&closure.name, location) {
+        (Some(name), _) => name.clone(),
+        (None, Some(location)) => format!("<lambda {}>", location),
+        (None, None) => "<lambda>".to_string()
+    };
+    Procedure::Closure(Rc::from(name))
+}
+
+// The forms of a parsed program, marked with where in `source` they were
+// written.
+fn located(parsed: Parsed, source: &str) -> Vec<Atom> {
+    let source = Rc::from(source);
+    let forms = match parsed.program {
+        Atom::List(forms) => forms,
+        _ => unreachable!()
+    };
+    forms.into_iter().zip(&parsed.spans).map(|(form, span)| locate(form, span, &source)).collect()
 }
 
 fn car(cdr: &[Atom]) -> Result<Atom, &'static str> {
     if cdr.len() != 1 { return Err("wrong number of args to car") }
-    match &cdr[0] {
-        &Atom::List(ref atoms) => Ok(atoms[0].clone()),
+    match cdr[0] {
+        Atom::List(ref atoms) if !atoms.is_empty() => Ok(atoms[0].clone()),
         _ => Err("invalid argument to car")
     }
 }
 
 fn cdr(cdr: &[Atom]) -> Result<Atom, &'static str> {
     match cdr.first() {
-        Some(&Atom::List(ref items)) if !items.is_empty() => {
+        Some(Atom::List(items)) if !items.is_empty() => {
             Ok(Atom::List(items[1..].to_vec()))
         },
         _ => Err("invalid cdr param")
@@ -282,8 +1866,8 @@ fn cdr(cdr: &[Atom]) -> Result<Atom, &'static str> {
 
 fn cons(cdr: &[Atom]) -> Result<Atom, &'static str> {
     if cdr.len() != 2 { return Err("wrong number of args for cons ") }
-    match &cdr[1] {
-        &Atom::List(ref vals) => Ok(Atom::List(prepend(cdr[0].clone(), &mut vals.clone()))),
+    match cdr[1] {
+        Atom::List(ref vals) => Ok(Atom::List(prepend(cdr[0].clone(), &mut vals.clone()))),
         _ => Err("invalid type to cons() onto")
     }
 }
@@ -292,42 +1876,141 @@ fn list(cdr: &[Atom]) -> Result<Atom, &'static str> {
     Ok(Atom::List(cdr.to_vec()))
 }
 
-fn math(start: i64, reduce: &Fn(i64, &i64) -> i64, cdr: &[Atom])
+fn append(args: &[Atom]) -> BasicResult<Atom> {
+    let lists = extract!(Atom::List, args);
+    Ok(Atom::List(lists.concat()))
+}
+
+fn reverse(args: &[Atom]) -> BasicResult<Atom> {
+    match args {
+        [Atom::List(items)] => Ok(Atom::List(items.iter().rev().cloned().collect())),
+        _ => Err("reverse requires a list")
+    }
+}
+
+fn length(args: &[Atom]) -> BasicResult<Atom> {
+    match args {
+        [Atom::List(items)] => Ok(Atom::Integer(items.len() as i64)),
+        _ => Err("length requires a list")
+    }
+}
+
+fn list_ref(args: &[Atom]) -> BasicResult<Atom> {
+    match args {
+        [Atom::List(items), Atom::Integer(k)] if *k >= 0 => {
+            items.get(*k as usize).cloned().ok_or("list-ref index out of range")
+        },
+        [Atom::List(_), Atom::Integer(_)] => Err("list-ref index out of range"),
+        _ => Err("list-ref requires a list and an index")
+    }
+}
+
+// Roughly how many cells of list a built in is about to make, from the
+// lists it was given, so that they can be counted before they are made.
+fn allocation(name: &str, args: &[Atom]) -> usize {
+    let lengths = || args.iter().map(|arg| match *arg {
+        Atom::List(ref items) => items.len(),
+        _ => 0
+    }).sum();
+    match name {
+        "iota" => match args.first() {
+            Some(&Atom::Integer(count)) if count > 0 => count as usize,
+            _ => 0
+        },
+        "list" => args.len(),
+        "cons" | "cdr" | "append" | "reverse" | "sort" | "map" | "filter" | "apply" => lengths(),
+        _ => 0
+    }
+}
+
+// (iota count start step) lists count integers from start, or 0, each step,
+// or 1, more than the last.
+fn iota(args: &[Atom]) -> BasicResult<Atom> {
+    let ints = extract!(Atom::Integer, args);
+    let (count, start, step) = match ints.as_slice() {
+        [count] => (*count, 0, 1),
+        [count, start] => (*count, *start, 1),
+        [count, start, step] => (*count, *start, *step),
+        _ => return Err("invalid arity for iota")
+    };
+    if count < 0 { return Err("iota requires a count of at least zero") }
+    // Stepping from one element to the next overflows only if an element
+    // would.
+    let mut atoms = vec![];
+    let mut next = Some(start);
+    for _ in 0..count {
+        let value = next.ok_or("integer overflow in iota")?;
+        atoms.push(Atom::Integer(value));
+        next = value.checked_add(step);
+    }
+    Ok(Atom::List(atoms))
+}
+
+// Folds the integers in `cdr` with `reduce`, which gives None on overflow.
+fn math(start: i64, reduce: fn(i64, i64) -> Option<i64>, cdr: &[Atom])
     -> Result<Atom, &'static str> {
-    if cdr.len() == 0 { return Err("attempted math on empty list") }
+    if cdr.is_empty() { return Err("attempted math on empty list") }
     let ints = extract!(Atom::Integer, cdr);
-    Ok(Atom::Integer(ints.iter().fold(start, reduce)))
+    fold_checked(start, reduce, &ints)
 }
 
-fn math_first(reduce: &Fn(i64, &i64) -> i64, cdr: &[Atom]) 
+fn math_first(reduce: fn(i64, i64) -> Option<i64>, cdr: &[Atom])
     -> Result<Atom, &'static str> {
     let ints = extract!(Atom::Integer, cdr);
-    if !ints.is_empty() {
-        Ok(Atom::Integer(ints[1..].iter().fold(ints[0], reduce)))
-    } else {
-        Err("attempted math on empty list")
+    match ints.split_first() {
+        Some((&first, rest)) => fold_checked(first, reduce, rest),
+        None => Err("attempted math on empty list")
     }
 }
 
+fn fold_checked(start: i64, reduce: fn(i64, i64) -> Option<i64>, ints: &[i64]) -> BasicResult<Atom> {
+    let mut total = start;
+    for &int in ints {
+        total = reduce(total, int).ok_or("integer overflow")?;
+    }
+    Ok(Atom::Integer(total))
+}
+
+fn divide(args: &[Atom]) -> BasicResult<Atom> {
+    let ints = extract!(Atom::Integer, args);
+    let (first, rest) = ints.split_first().ok_or("attempted math on empty list")?;
+    let mut quotient = *first;
+    for &divisor in rest {
+        quotient = match quotient.checked_div(divisor) {
+            Some(quotient) => quotient,
+            None if divisor == 0 => return Err("division by zero"),
+            // The only other way to fail, dividing the most negative
+            // integer by -1.
+            None => return Err("integer overflow in division")
+        };
+    }
+    Ok(Atom::Integer(quotient))
+}
+
 fn gt(args: &[Atom]) -> BasicResult<Atom> {
-    binary_int_predicate(args, &|a, b| a > b)
+    compare(args, &|a, b| a > b)
 }
 
 
 fn lt(args: &[Atom]) -> BasicResult<Atom> {
-    binary_int_predicate(args, &|a, b| a < b)
+    compare(args, &|a, b| a < b)
 }
 
 fn eq(args: &[Atom]) -> BasicResult<Atom> {
-    binary_int_predicate(args, &|a, b| a == b)
+    compare(args, &|a, b| a == b)
 }
 
-fn binary_int_predicate(args: &[Atom], op: &Fn(i64, i64) -> bool) -> BasicResult<Atom> {
-    if args.len() != 2 { return Err("invalid arity for binary number predicate") }
-    match (&args[0], &args[1]) {
-        (&Atom::Integer(one), &Atom::Integer(two)) => Ok(lithp_bool(op(one, two))),
-        _ => Err("invalid arguments to binary number predicate")
+// Holds if op holds for each number and the one after it, as in (< 1 2 3).
+fn compare(args: &[Atom], op: &dyn Fn(i64, i64) -> bool) -> BasicResult<Atom> {
+    if args.is_empty() { return Err("invalid arity for number comparison") }
+    let mut ints = Vec::with_capacity(args.len());
+    for arg in args {
+        match *arg {
+            Atom::Integer(int) => ints.push(int),
+            _ => return Err("invalid arguments to number comparison")
+        }
     }
+    Ok(lithp_bool(ints.windows(2).all(|pair| op(pair[0], pair[1]))))
 }
 
 
@@ -345,6 +2028,272 @@ fn equal(args: &[Atom]) -> BasicResult<Atom> {
     Ok(lithp_bool(args[0] == args[1]))
 }
 
+// `eqv?` is the same as `eq?`, as integers, the only numbers, are compared by
+// value either way.
+fn identical(args: &[Atom]) -> BasicResult<Atom> {
+    if args.len() != 2 { return Err("invalid arity for eq?") }
+    Ok(lithp_bool(args[0].is_identical(&args[1])))
+}
+
+// (quote datum) is the same as 'datum.
+fn eval_quote(cdr: &[Atom]) -> StepResult {
+    match cdr {
+        [datum] => Ok(Control::Return(datum.clone())),
+        _ => Err(Error::from("quote takes exactly one datum"))
+    }
+}
+
+fn parse_parameters(params: &Atom) -> Result<Parameters, Error> {
+    let params = match *params {
+        Atom::Identifier(ref rest) => {
+            return Ok(Parameters { rest: Some(rest.clone()), ..Parameters::default() })
+        },
+        Atom::List(ref params) => params,
+        _ => return Err(Error::from("invalid lambda"))
+    };
+
+    let mut parameters = Parameters::default();
+    let mut section = "";
+    let mut params = params.iter();
+    while let Some(param) = params.next() {
+        match *param {
+            Atom::Identifier(ref marker) if marker == "#!optional" || marker == "#!key" => {
+                section = marker;
+            },
+            Atom::Identifier(ref marker) if marker == "." || marker == "#!rest" => {
+                match params.next() {
+                    Some(Atom::Identifier(rest)) if parameters.rest.is_none() => {
+                        parameters.rest = Some(rest.clone())
+                    },
+                    _ => return Err(Error::new("invalid rest parameter", vec![param.clone()]))
+                }
+            },
+            Atom::Identifier(ref name) => match section {
+                "#!optional" => parameters.optional.push((name.clone(), None)),
+                "#!key" => parameters.keys.push((name.clone(), None)),
+                _ if parameters.rest.is_some() => {
+                    return Err(Error::new("parameter after rest parameter", vec![param.clone()]))
+                },
+                _ => parameters.required.push(name.clone())
+            },
+            Atom::List(ref pair) if pair.len() == 2 && !section.is_empty() => {
+                let default = match pair[0] {
+                    Atom::Identifier(ref name) => (name.clone(), Some(pair[1].clone())),
+                    _ => return Err(Error::new("invalid lambda parameter", vec![param.clone()]))
+                };
+                if section == "#!key" {
+                    parameters.keys.push(default);
+                } else {
+                    parameters.optional.push(default);
+                }
+            },
+            _ => return Err(Error::new("invalid lambda parameter", vec![param.clone()]))
+        }
+    }
+    Ok(parameters)
+}
+
+// Keywords such as #:width name keyword arguments, and evaluate to themselves.
+fn is_keyword(atom: &Atom) -> bool {
+    match *atom {
+        Atom::Identifier(ref name) => name.len() > 2 && name.starts_with("#:"),
+        _ => false
+    }
+}
+
+fn default_value(default: &Option<Atom>) -> Atom {
+    default.clone().unwrap_or_else(lithp_false)
+}
+
+// Reads the first datum written in a string.
+fn read_string(args: &[Atom], max_depth: usize) -> BasicResult<Atom> {
+    match args {
+        [Atom::String(string)] => {
+            let datum = Parser::new(tokenize(string)?).max_depth(max_depth).parse_datum()?;
+            Ok(datum.unwrap_or_else(eof_object))
+        },
+        _ => Err("read-string requires a string")
+    }
+}
+
+fn close_port(args: &[Atom]) -> BasicResult<Atom> {
+    match args {
+        [Atom::Port(port)] => {
+            port.borrow_mut().close()?;
+            Ok(unspecified())
+        },
+        _ => Err("closing a port requires a port")
+    }
+}
+
+fn is_eof_object(args: &[Atom]) -> BasicResult<Atom> {
+    if args.len() != 1 { return Err("invalid arity for eof-object?") }
+    Ok(lithp_bool(args[0] == eof_object()))
+}
+
+// Returns what a built in that only computes a value computed.
+fn value(value: BasicResult<Atom>) -> StepResult {
+    Ok(Control::Return(value?))
+}
+
+fn single(mut args: Vec<Atom>) -> Result<Atom, Error> {
+    if args.len() != 1 { return Err(Error::from("expected exactly one argument")) }
+    Ok(args.pop().unwrap())
+}
+
+fn make_error(args: Vec<Atom>) -> Result<Error, Error> {
+    match args.split_first() {
+        Some((Atom::String(message), irritants)) => Ok(Error::new(message, irritants.to_vec())),
+        _ => Err(Error::from("error requires a message string"))
+    }
+}
+
+fn error_object(args: &[Atom]) -> BasicResult<Atom> {
+    if args.len() != 1 { return Err("invalid arity for error-object?") }
+    match args[0] {
+        Atom::Condition(_) => Ok(lithp_true()),
+        _ => Ok(lithp_false())
+    }
+}
+
+fn error_object_message(args: &[Atom]) -> BasicResult<Atom> {
+    match args {
+        [Atom::Condition(condition)] => Ok(Atom::String(Rc::from(condition.message.as_str()))),
+        _ => Err("error-object-message requires an error object")
+    }
+}
+
+fn error_object_irritants(args: &[Atom]) -> BasicResult<Atom> {
+    match args {
+        [Atom::Condition(condition)] => Ok(Atom::List(condition.irritants.clone())),
+        _ => Err("error-object-irritants requires an error object")
+    }
+}
+
+// What `member` or `assoc` compare an element of their list by.
+fn find_key(current: &Atom, assoc: bool) -> Result<Atom, Error> {
+    if !assoc {
+        return Ok(current.clone())
+    }
+    match *current {
+        Atom::List(ref pair) if !pair.is_empty() => Ok(pair[0].clone()),
+        _ => Err(Error::new("assoc requires a list of pairs", vec![current.clone()]))
+    }
+}
+
+// What `member` or `assoc` return on finding `current`, with the elements
+// after it in `pending` in reverse.
+fn found(current: Atom, pending: Vec<Atom>, assoc: bool) -> Atom {
+    if assoc {
+        return current
+    }
+    let mut tail = vec![current];
+    tail.extend(pending.into_iter().rev());
+    Atom::List(tail)
+}
+
+// Gives a procedure being bound the name it is bound to, unless it has one.
+fn named(value: Atom, name: &str) -> Atom {
+    match value {
+        Atom::Lambda(mut closure) if closure.name.is_none() => {
+            closure.name = Some(name.to_string());
+            Atom::Lambda(closure)
+        },
+        value => value
+    }
+}
+
+fn is_type(args: &[Atom], test: fn(&Atom) -> bool) -> BasicResult<Atom> {
+    match args {
+        [arg] => Ok(lithp_bool(test(arg))),
+        _ => Err("type predicates take exactly one argument")
+    }
+}
+
+// Identifiers other than booleans, keywords and the special objects that
+// are written with #.
+fn is_symbol(atom: &Atom) -> bool {
+    match *atom {
+        Atom::Identifier(ref name) => !name.is_empty() && !name.starts_with('#'),
+        _ => false
+    }
+}
+
+fn is_boolean(atom: &Atom) -> bool {
+    *atom == lithp_true() || *atom == lithp_false()
+}
+
+fn is_pair(atom: &Atom) -> bool {
+    matches!(*atom, Atom::List(ref items) if !items.is_empty())
+}
+
+fn is_null(atom: &Atom) -> bool {
+    matches!(*atom, Atom::List(ref items) if items.is_empty())
+}
+
+fn is_input_port(atom: &Atom) -> bool {
+    matches!(*atom, Atom::Port(ref port) if port.borrow().is_input())
+}
+
+fn is_output_port(atom: &Atom) -> bool {
+    matches!(*atom, Atom::Port(ref port) if !port.borrow().is_input())
+}
+
+fn is_procedure(atom: &Atom) -> bool {
+    matches!(*atom, Atom::Builtin(_) | Atom::Lambda(_) | Atom::Continuation(_))
+}
+
+// (procedure-arity f) is (min max), the fewest and most arguments f may be
+// called with, where max is #f if there is no limit. Only the arity of
+// closures is known; anything else is taken to accept any number.
+fn procedure_arity(args: &[Atom]) -> BasicResult<Atom> {
+    let (min, max) = match args {
+        [Atom::Lambda(closure)] => {
+            let parameters = &closure.parameters;
+            let min = parameters.required.len();
+            let max = min + parameters.optional.len();
+            let unlimited = parameters.rest.is_some() || !parameters.keys.is_empty();
+            (min, if unlimited { None } else { Some(max) })
+        },
+        [arg] if is_procedure(arg) => (0, None),
+        _ => return Err("procedure-arity requires a procedure")
+    };
+    let max = max.map_or_else(lithp_false, |max| Atom::Integer(max as i64));
+    Ok(Atom::List(vec![Atom::Integer(min as i64), max]))
+}
+
+// (procedure-name f) is the name f was defined with, or #f if it has none.
+fn procedure_name(args: &[Atom]) -> BasicResult<Atom> {
+    match args {
+        [Atom::Builtin(name)] => Ok(Atom::Identifier(name.to_string())),
+        [Atom::Lambda(closure)] => Ok(closure.name.clone().map_or_else(lithp_false, Atom::Identifier)),
+        [Atom::Continuation(_)] => Ok(lithp_false()),
+        _ => Err("procedure-name requires a procedure")
+    }
+}
+
+// (current-second) is the whole seconds since the Unix epoch.
+fn current_second(args: &[Atom]) -> BasicResult<Atom> {
+    if !args.is_empty() { return Err("invalid arity for current-second") }
+    Ok(Atom::Integer(since_epoch().as_secs() as i64))
+}
+
+// (current-jiffy) is the microseconds since the Unix epoch, for timing code
+// with (jiffies-per-second) of them to a second.
+fn current_jiffy(args: &[Atom]) -> BasicResult<Atom> {
+    if !args.is_empty() { return Err("invalid arity for current-jiffy") }
+    Ok(Atom::Integer(since_epoch().as_micros() as i64))
+}
+
+fn jiffies_per_second(args: &[Atom]) -> BasicResult<Atom> {
+    if !args.is_empty() { return Err("invalid arity for jiffies-per-second") }
+    Ok(Atom::Integer(1_000_000))
+}
+
+fn since_epoch() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO)
+}
+
 fn lithp_bool(value: bool) -> Atom {
     if value { lithp_true() } else { lithp_false() }
 }
@@ -356,7 +2305,7 @@ fn extract_bindings(atom_list: Atom) -> BasicResult<Vec<(String, Atom)>> {
     };
     let mut bindings = Vec::with_capacity(atoms.len());
     for atom in atoms {
-        bindings.push(try!(extract_binding(atom)));
+        bindings.push(extract_binding(atom)?);
     }
     Ok(bindings)
 }
@@ -365,7 +2314,7 @@ fn extract_binding(atom: Atom) -> Result<(String, Atom), &'static str> {
     match atom {
         Atom::List(ref binding) if binding.len() == 2 => {
             match (&binding[0], &binding[1]) {
-                (&Atom::Identifier(ref name), value) => Ok((name.clone(), value.clone())),
+                (Atom::Identifier(name), value) => Ok((name.clone(), value.clone())),
                 _ => Err("binding must start with an identifier")
             }
         },
@@ -376,8 +2325,8 @@ fn extract_binding(atom: Atom) -> Result<(String, Atom), &'static str> {
 fn split_let_body(cdr: &[Atom]) -> Result<(&Atom, &[Atom]), &'static str> {
     match cdr.split_first() {
         Some((binding_list, expressions))
-            if expressions.len() >= 1 => Ok((binding_list, expressions)),
+            if !expressions.is_empty() => Ok((binding_list, expressions)),
         Some(_) => Err("invalid let(*) format"),
         None => Err("empty let(*)")
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,71 @@
+use std::mem;
+use pretty::{layout, list, program, Doc, Element};
+use syntax::{parse, Node};
+
+// Rewrites source code in the canonical style: forms indented as the pretty
+// printer indents them, lines wrapped to fit `width` where possible, and
+// comments and single empty lines kept where they were. Source nested more
+// than `max_depth` deep is an error, as it is for the parser.
+pub fn format(source: &str, width: usize, max_depth: usize) -> Result<String, &'static str> {
+    let elements = elements(&parse(source, max_depth)?);
+    if elements.is_empty() {
+        return Ok(String::new())
+    }
+    let mut formatted = layout(&program(elements), width);
+    formatted.push('\n');
+    Ok(formatted)
+}
+
+// Turns a sequence of nodes into elements, attaching each comment to the
+// element it belongs with.
+fn elements(nodes: &[Node]) -> Vec<Element> {
+    let mut elements: Vec<Element> = vec![];
+    let mut comments = vec![];
+    // Line breaks since the last element or comment.
+    let mut newlines = 0;
+    for node in nodes {
+        match *node {
+            Node::Whitespace(ref space) => newlines += space.matches('\n').count(),
+            Node::Comment(ref text) => {
+                match elements.last_mut() {
+                    Some(last) if newlines == 0 && comments.is_empty() && last.comment_after.is_none() => {
+                        last.comment_after = Some(text.clone());
+                    },
+                    _ => comments.push((newlines > 1, text.clone()))
+                }
+                newlines = 0;
+            },
+            ref node => {
+                let mut element = element(node);
+                comments.append(&mut element.comments_before);
+                element.comments_before = mem::take(&mut comments);
+                element.blank_before = newlines > 1;
+                elements.push(element);
+                newlines = 0;
+            }
+        }
+    }
+    // Comments after the last element are laid out as elements themselves.
+    if let Some((blank, text)) = comments.pop() {
+        let mut element = Element::comment(text);
+        element.comments_before = comments;
+        element.blank_before = blank;
+        elements.push(element);
+    }
+    elements
+}
+
+fn element(node: &Node) -> Element {
+    match *node {
+        Node::List(ref nodes) => Element::new(list(elements(nodes)), None, true),
+        Node::Quote(ref nodes) => {
+            // Any comments between the quote and its datum go before both.
+            let mut datum = elements(nodes).pop().expect("a quote always has a datum");
+            datum.doc = Doc::Concat(vec![Doc::Text("'".to_string()), datum.doc]);
+            datum.name = None;
+            datum.blank_before = false;
+            datum
+        },
+        ref token => Element::new(Doc::Text(token.to_string()), token.identifier(), false)
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+use atom::Atom;
+use continuation::Continuation;
+use port::{Port, PortRef};
+use scope::{Scope, ScopeRef};
+
+// Number of scopes that may be allocated between automatic collections.
+const DEFAULT_GC_THRESHOLD: usize = 1024;
+
+// Implemented by anything that can hold on to heap scopes, so that the
+// collector can find every scope reachable from the evaluator's state.
+pub trait Trace {
+    fn trace(&self, tracer: &mut Tracer);
+}
+
+// Finds what is reachable, keeping its own lists of what is left to look at
+// rather than recursing, so that neither nested lists nor continuations
+// captured within continuations use up the native stack. Along the way it
+// counts the cells of the lists and strings it finds.
+pub struct Tracer {
+    pending: Vec<ScopeRef>,
+    continuations: Vec<Rc<Continuation>>,
+    // Continuations are shared and may be captured many times over, so each
+    // is only traced once per collection. The same goes for strings, which
+    // are only counted once.
+    seen: HashSet<*const Continuation>,
+    strings: HashSet<*const u8>,
+    cells: usize
+}
+
+impl Tracer {
+    pub fn scope(&mut self, scope: ScopeRef) {
+        self.pending.push(scope);
+    }
+
+    pub fn atom(&mut self, atom: &Atom) {
+        let mut atoms = vec![atom];
+        while let Some(atom) = atoms.pop() {
+            match *atom {
+                Atom::List(ref items) => {
+                    self.cells += items.len();
+                    atoms.extend(items);
+                },
+                // Forms are the program itself rather than anything it made.
+                Atom::Form(_, ref items) => atoms.extend(items),
+                Atom::Quoted(ref atom) => atoms.push(atom),
+                Atom::String(ref string) => {
+                    if self.strings.insert(string.as_ptr()) {
+                        self.cells += string.len();
+                    }
+                },
+                Atom::Lambda(ref closure) => self.scope(closure.scope),
+                Atom::Continuation(ref continuation) => self.continuation(continuation),
+                Atom::Condition(ref condition) => atoms.extend(&condition.irritants),
+                Atom::Port(ref port) => self.port(port),
+                Atom::Integer(_) | Atom::Identifier(_) | Atom::Builtin(_) => ()
+            }
+        }
+    }
+
+    pub fn atoms(&mut self, atoms: &[Atom]) {
+        for atom in atoms {
+            self.atom(atom);
+        }
+    }
+
+    pub fn continuation(&mut self, continuation: &Rc<Continuation>) {
+        if self.seen.insert(&**continuation as *const Continuation) {
+            self.continuations.push(continuation.clone());
+        }
+    }
+
+    // What has been written to a string port so far counts as a string.
+    pub fn port(&mut self, port: &PortRef) {
+        if let Port::StringOutput(ref output) = *port.borrow() {
+            self.cells += output.len();
+        }
+    }
+}
+
+impl Trace for Atom {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.atom(self);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GcStats {
+    pub collections: usize,
+    pub allocated: usize,
+    pub freed: usize,
+    pub live: usize
+}
+
+// Owns every scope, so that closures and the scopes they capture may refer
+// to each other freely. Unreachable scopes are reclaimed by mark and sweep.
+// It also keeps count of the cells of lists and strings, which live outside
+// it, for limiting how much memory a program uses.
+#[derive(Debug)]
+pub struct Heap {
+    scopes: Vec<Option<Scope<Atom>>>,
+    // How many times the scope in each slot has been collected.
+    generations: Vec<u32>,
+    marks: Vec<bool>,
+    free: Vec<usize>,
+    allocated_since_gc: usize,
+    threshold: usize,
+    stats: GcStats,
+    // The cells of lists and strings found reachable by the last collection,
+    // and those counted as allocated since.
+    data_cells: usize
+}
+
+impl Default for Heap {
+    fn default() -> Heap {
+        Heap::new()
+    }
+}
+
+impl Heap {
+    pub fn new() -> Heap {
+        Heap {
+            scopes: Vec::new(),
+            generations: Vec::new(),
+            marks: Vec::new(),
+            free: Vec::new(),
+            allocated_since_gc: 0,
+            threshold: DEFAULT_GC_THRESHOLD,
+            stats: GcStats::default(),
+            data_cells: 0
+        }
+    }
+
+    pub fn new_scope(&mut self, parent: Option<ScopeRef>) -> ScopeRef {
+        self.allocated_since_gc += 1;
+        self.stats.allocated += 1;
+        self.stats.live += 1;
+        let scope = Some(Scope::new(parent));
+        match self.free.pop() {
+            Some(index) => {
+                self.scopes[index] = scope;
+                ScopeRef { index, generation: self.generations[index] }
+            },
+            None => {
+                self.scopes.push(scope);
+                self.generations.push(0);
+                self.marks.push(false);
+                ScopeRef { index: self.scopes.len() - 1, generation: 0 }
+            }
+        }
+    }
+
+    pub fn new_child_scope(&mut self, parent: ScopeRef) -> ScopeRef {
+        self.new_scope(Some(parent))
+    }
+
+    // Whether `scope` is still on the heap, rather than collected.
+    pub fn is_live(&self, scope: ScopeRef) -> bool {
+        self.generations.get(scope.index) == Some(&scope.generation) && self.scopes[scope.index].is_some()
+    }
+
+    pub fn scope(&self, scope: ScopeRef) -> &Scope<Atom> {
+        assert!(self.is_live(scope), "use of collected scope");
+        self.scopes[scope.index].as_ref().unwrap()
+    }
+
+    pub fn scope_mut(&mut self, scope: ScopeRef) -> &mut Scope<Atom> {
+        assert!(self.is_live(scope), "use of collected scope");
+        self.scopes[scope.index].as_mut().unwrap()
+    }
+
+    pub fn get(&self, scope: ScopeRef, name: &str) -> Option<Atom> {
+        let mut current = Some(scope);
+        while let Some(scope) = current {
+            let scope = self.scope(scope);
+            if let Some(value) = scope.get_local(name) {
+                return Some(value);
+            }
+            current = scope.parent;
+        }
+        None
+    }
+
+    pub fn set_local(&mut self, scope: ScopeRef, name: &str, value: Atom) {
+        self.scope_mut(scope).set_local(name, value);
+    }
+
+    // Rebinds `name` in the nearest scope that binds it, returning false if
+    // none do.
+    pub fn set_inherited(&mut self, scope: ScopeRef, name: &str, value: Atom) -> bool {
+        let mut current = Some(scope);
+        while let Some(scope) = current {
+            if self.scope(scope).has_local(name) {
+                self.set_local(scope, name, value);
+                return true;
+            }
+            current = self.scope(scope).parent;
+        }
+        false
+    }
+
+    pub fn should_collect(&self) -> bool {
+        self.allocated_since_gc >= self.threshold
+    }
+
+    pub fn stats(&self) -> GcStats {
+        self.stats
+    }
+
+    // Roughly how much memory is in use: a cell for each scope, and for each
+    // element of a list or byte of a string. Garbage made since the last
+    // collection is counted until the next one.
+    pub fn cells(&self) -> usize {
+        self.stats.live + self.data_cells
+    }
+
+    // Counts cells of lists or strings about to be made.
+    pub fn allocate_cells(&mut self, cells: usize) {
+        self.data_cells = self.data_cells.saturating_add(cells);
+    }
+
+    // Frees every scope not reachable from `roots`, returning how many were
+    // reclaimed.
+    pub fn collect(&mut self, roots: &dyn Trace) -> usize {
+        let mut tracer = Tracer {
+            pending: Vec::new(),
+            continuations: Vec::new(),
+            seen: HashSet::new(),
+            strings: HashSet::new(),
+            cells: 0
+        };
+        roots.trace(&mut tracer);
+
+        loop {
+            if let Some(continuation) = tracer.continuations.pop() {
+                continuation.trace(&mut tracer);
+                continue;
+            }
+            let scope = match tracer.pending.pop() {
+                Some(scope) => scope,
+                None => break
+            };
+            // A reference to a scope already collected, which only an
+            // embedder holding on to a value could have, keeps nothing.
+            if !self.is_live(scope) || self.marks[scope.index] { continue }
+            self.marks[scope.index] = true;
+            let scope = self.scope(scope);
+            if let Some(parent) = scope.parent {
+                tracer.scope(parent);
+            }
+            for value in scope.values() {
+                value.trace(&mut tracer);
+            }
+        }
+
+        let mut freed = 0;
+        for index in 0..self.scopes.len() {
+            if self.marks[index] {
+                self.marks[index] = false;
+            } else if self.scopes[index].is_some() {
+                self.scopes[index] = None;
+                self.generations[index] = self.generations[index].wrapping_add(1);
+                self.free.push(index);
+                freed += 1;
+            }
+        }
+
+        self.allocated_since_gc = 0;
+        self.data_cells = tracer.cells;
+        self.stats.collections += 1;
+        self.stats.freed += freed;
+        self.stats.live -= freed;
+        freed
+    }
+}
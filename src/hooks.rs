@@ -0,0 +1,15 @@
+use atom::{Atom, Closure};
+use eval::Interpreter;
+use scope::ScopeRef;
+use source::Location;
+
+// Lets a tool such as a debugger follow evaluation as it goes. An interpreter
+// given hooks calls them with itself, to be inspected or to evaluate more
+// code with; one without them does no extra work.
+pub trait Hooks {
+    // `form`, written at `location`, is about to be evaluated in `scope`.
+    fn form(&mut self, interpreter: &mut Interpreter, scope: ScopeRef, form: &Atom, location: &Location);
+
+    // `closure` is being called, before its body is evaluated.
+    fn call(&mut self, _closure: &Closure) {}
+}
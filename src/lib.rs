@@ -0,0 +1,25 @@
+// The lithp interpreter, for embedding in other programs as well as for the
+// lithp command.
+pub mod util;
+pub mod diagnostic;
+pub mod atom;
+pub mod capability;
+pub mod scope;
+pub mod heap;
+pub mod hooks;
+pub mod port;
+pub mod module;
+pub mod continuation;
+pub mod debugger;
+pub mod error;
+pub mod analyze;
+pub mod eval;
+pub mod tokenizer;
+pub mod parser;
+pub mod printer;
+pub mod profile;
+pub mod pretty;
+pub mod reader;
+pub mod source;
+pub mod syntax;
+pub mod format;
@@ -1,29 +1,322 @@
-mod util;
-mod atom;
-mod scope;
-mod eval;
-mod tokenizer;
-mod parser;
-
-use tokenizer::tokenize;
-use parser::Parser;
-use eval::eval;
-use std::io::{self, Read};
+extern crate lithp;
+
+use lithp::parser::{parse_program, DEFAULT_MAX_DEPTH};
+use lithp::eval::{Interpreter, Limits, Options};
+use lithp::atom::Atom;
+use lithp::capability::Capability;
+use lithp::debugger::Debugger;
+use lithp::diagnostic::Diagnostic;
+use lithp::error::{Error, Traceback};
+use lithp::format;
+use lithp::pretty::{pretty, DEFAULT_WIDTH};
+use lithp::profile::Report;
+use lithp::reader::Reader;
+use lithp::source::locate;
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
 
 fn main() {
-    let mut program = String::new();
-    io::stdin().read_to_string(&mut program).unwrap();
-    let tokens = tokenize(&program)
-        .expect("invalid tokens given");
-    let result = match Parser::new(tokens).parse() {
-        Ok(ast) => eval(ast),
-        Err(msg) => {
-            println!("Syntax Error: {}", msg);
-            return;
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("fmt") => {
+            args.remove(0);
+            return fmt(args);
+        },
+        Some("check") => {
+            args.remove(0);
+            return check(args);
+        },
+        _ => ()
+    }
+    let (flags, paths): (Vec<String>, Vec<String>) = args.into_iter()
+        .partition(|arg| arg.starts_with("--"));
+    let max_depth = number_flag(&flags, "max-depth").unwrap_or(DEFAULT_MAX_DEPTH);
+    let limits = Limits {
+        steps: number_flag(&flags, "max-steps"),
+        cells: number_flag(&flags, "max-cells"),
+        time: number_flag(&flags, "timeout").map(Duration::from_millis)
+    };
+    // Files are looked for in the directories given with --path, then the
+    // program's directory, then the current one.
+    let mut search_path: Vec<PathBuf> = flags.iter()
+        .filter_map(|flag| flag.strip_prefix("--path="))
+        .map(PathBuf::from)
+        .collect();
+    if let Some(directory) = paths.first().and_then(|path| Path::new(path).parent()) {
+        search_path.push(directory.to_path_buf());
+    }
+    search_path.push(PathBuf::from("."));
+    let options = Options {
+        pure: flags.iter().any(|flag| flag == "--pure"),
+        max_depth,
+        search_path,
+        prelude: !flags.iter().any(|flag| flag == "--no-prelude"),
+        limits,
+        capabilities: match capabilities(&flags) {
+            Some(capabilities) => capabilities,
+            None => process::exit(1)
         }
     };
-    match result {
-        Err(msg) => println!("Evaluation Error: {}", msg),
-        Ok(_) => ()
+    let mut interpreter = Interpreter::with_options(options);
+    if flags.iter().any(|flag| flag == "--list-built-ins") {
+        println!("allowed: {}", interpreter.allowed_built_ins().join(" "));
+        println!("denied: {}", interpreter.denied_built_ins().join(" "));
+        return;
+    }
+    // The debugger takes its commands from standard input, so the program
+    // has to come from a file.
+    if flags.iter().any(|flag| flag == "--debug") {
+        if paths.is_empty() {
+            println!("Error: --debug needs a program file");
+            process::exit(1);
+        }
+        interpreter.set_hooks(Box::new(Debugger::new()));
+    }
+    // With --profile the program's calls are measured, and once it has run a
+    // report goes to standard error and the stacks called to a file for
+    // flame graph tools, lithp.folded unless named with --profile=FILE.
+    let profile = flags.iter().find_map(|flag| match flag.as_str() {
+        "--profile" => Some("lithp.folded"),
+        flag => flag.strip_prefix("--profile=")
+    });
+    if profile.is_some() {
+        interpreter.start_profiling();
+    }
+    // With no file given, a terminal on standard input gets a REPL.
+    if flags.iter().any(|flag| flag == "--repl") ||
+        (paths.is_empty() && io::stdin().is_terminal()) {
+        return repl(interpreter);
+    }
+    // The program is read from the file given, or from standard input, and
+    // each form is evaluated as soon as it has been read.
+    interpreter.reset_limits();
+    let value = match paths.first() {
+        Some(path) => match File::open(path) {
+            Ok(file) => {
+                let mut reader = Reader::new(BufReader::new(file)).max_depth(max_depth);
+                let source = Rc::from(path.as_str());
+                run(&mut interpreter, |_| {
+                    Ok(reader.read_spanned()?.map(|(form, span)| locate(form, &span, &source)))
+                })
+            },
+            Err(error) => {
+                println!("Error: {}", error);
+                return;
+            }
+        },
+        None => run(&mut interpreter, Interpreter::read_form)
+    };
+    if let Some(value) = value {
+        print_value(&value);
+    }
+    if let (Some(path), Some(profiler)) = (profile, interpreter.profiler()) {
+        eprint!("{}", Report(profiler));
+        if let Err(error) = fs::write(path, profiler.folded()) {
+            println!("Error: {}: {}", path, error);
+        }
+    }
+}
+
+// Evaluates forms in order until there are none left, returning the value
+// of the last, or stopping at the first error.
+fn run<F>(interpreter: &mut Interpreter, mut read: F) -> Option<Atom>
+    where F: FnMut(&mut Interpreter) -> Result<Option<Atom>, Diagnostic> {
+    let mut value = None;
+    loop {
+        match read(interpreter) {
+            Ok(Some(form)) => match interpreter.eval_form(form) {
+                Ok(result) => value = Some(result),
+                Err(error) => {
+                    report(&error);
+                    return None;
+                }
+            },
+            Ok(None) => return value,
+            Err(diagnostic) => {
+                println!("Syntax Error: {}", diagnostic);
+                return None;
+            }
+        }
+    }
+}
+
+// Evaluates each datum typed in the global scope as it is read, so
+// definitions carry over, printing any value it has.
+fn repl(mut interpreter: Interpreter) {
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+        let datum = match interpreter.read_form() {
+            Ok(Some(datum)) => datum,
+            Ok(None) => {
+                println!();
+                return;
+            },
+            Err(diagnostic) => {
+                println!("Syntax Error: {}", diagnostic);
+                continue;
+            }
+        };
+        interpreter.reset_limits();
+        match interpreter.eval_form(datum) {
+            Err(error) => report(&error),
+            Ok(value) => print_value(&value)
+        }
+    }
+}
+
+// Prints an error nothing handled, after the calls it was raised in.
+fn report(error: &Error) {
+    if !error.trace.is_empty() {
+        print!("{}", Traceback(&error.trace));
+    }
+    println!("Evaluation Error: {}", error);
+}
+
+// Prints a result, unless it has no value to speak of.
+fn print_value(value: &Atom) {
+    match *value {
+        Atom::Identifier(ref name) if name.is_empty() => (),
+        ref value => println!("{}", pretty(value, DEFAULT_WIDTH))
+    }
+}
+
+// The capabilities given with --allow=pure,io-write,..., or all of them.
+fn capabilities(flags: &[String]) -> Option<HashSet<Capability>> {
+    let names = match flags.iter().find_map(|flag| flag.strip_prefix("--allow=")) {
+        Some(names) => names,
+        None => return Some(Capability::ALL.iter().cloned().collect())
+    };
+    names.split(',').filter(|name| !name.is_empty()).map(|name| {
+        let capability = Capability::from_name(name);
+        if capability.is_none() {
+            let names: Vec<_> = Capability::ALL.iter().map(|capability| capability.name()).collect();
+            println!("Error: unknown capability {}, expected one of {}", name, names.join(", "));
+        }
+        capability
+    }).collect()
+}
+
+// The value of a flag such as --max-depth=N, if given, exiting if it isn't a
+// number.
+fn number_flag<T: FromStr>(flags: &[String], name: &str) -> Option<T> {
+    let prefix = format!("--{}=", name);
+    flags.iter().find_map(|flag| flag.strip_prefix(&prefix)).map(|value| match value.parse() {
+        Ok(value) => value,
+        Err(_) => {
+            println!("Error: --{} must be a number", name);
+            process::exit(1);
+        }
+    })
+}
+
+// Reports every syntax error in the files given, or in standard input,
+// without running anything, exiting non-zero if there were any. With --pure
+// the forms are also checked as pure mode checks them.
+fn check(args: Vec<String>) {
+    let (flags, paths): (Vec<String>, Vec<String>) = args.into_iter()
+        .partition(|arg| arg.starts_with("--"));
+    let max_depth = number_flag(&flags, "max-depth").unwrap_or(DEFAULT_MAX_DEPTH);
+    let options = Options {
+        pure: flags.iter().any(|flag| flag == "--pure"),
+        max_depth,
+        prelude: !flags.iter().any(|flag| flag == "--no-prelude"),
+        ..Options::default()
+    };
+    let mut sources = vec![];
+    if paths.is_empty() {
+        let mut source = String::new();
+        sources.push((None, io::stdin().read_to_string(&mut source).map(|_| source)));
+    }
+    for path in paths {
+        let source = fs::read_to_string(&path);
+        sources.push((Some(path), source));
+    }
+    let mut failed = false;
+    for (path, source) in sources {
+        let prefix = path.map(|path| format!("{}: ", path)).unwrap_or_default();
+        match source {
+            Ok(source) => {
+                let parsed = parse_program(&source, max_depth);
+                for diagnostic in &parsed.diagnostics {
+                    println!("{}Syntax Error: {}", prefix, diagnostic);
+                    failed = true;
+                }
+                // What could be parsed is checked too, as it would be run.
+                if let Atom::List(ref forms) = parsed.program {
+                    if let Err(msg) = Interpreter::with_options(options.clone()).check(forms) {
+                        println!("{}Error: {}", prefix, msg);
+                        failed = true;
+                    }
+                }
+            },
+            Err(error) => {
+                println!("{}Error: {}", prefix, error);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+// Formats the files given in place, or standard input to standard output.
+// With --check nothing is written, and the exit status is non-zero if any
+// file isn't formatted already.
+fn fmt(args: Vec<String>) {
+    let (flags, paths): (Vec<String>, Vec<String>) = args.into_iter()
+        .partition(|arg| arg.starts_with("--"));
+    let check = flags.iter().any(|flag| flag == "--check");
+    let max_depth = number_flag(&flags, "max-depth").unwrap_or(DEFAULT_MAX_DEPTH);
+    let mut failed = false;
+    if paths.is_empty() {
+        let mut source = String::new();
+        if let Err(error) = io::stdin().read_to_string(&mut source) {
+            println!("Error: {}", error);
+            process::exit(1);
+        }
+        match format::format(&source, DEFAULT_WIDTH, max_depth) {
+            Ok(formatted) if check => failed = formatted != source,
+            Ok(formatted) => print!("{}", formatted),
+            Err(msg) => {
+                println!("Syntax Error: {}", msg);
+                failed = true;
+            }
+        }
+    }
+    for path in &paths {
+        let formatted = fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|source| {
+                let formatted = format::format(&source, DEFAULT_WIDTH, max_depth)
+                    .map_err(|msg| format!("Syntax Error: {}", msg))?;
+                Ok((formatted != source, formatted))
+            });
+        match formatted {
+            Ok((false, _)) => (),
+            Ok((true, _)) if check => {
+                println!("{}: not formatted", path);
+                failed = true;
+            },
+            Ok((true, formatted)) => if let Err(error) = fs::write(path, formatted) {
+                println!("{}: Error: {}", path, error);
+                failed = true;
+            },
+            Err(error) => {
+                println!("{}: {}", path, error);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
     }
 }
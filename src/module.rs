@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use atom::Atom;
+use error::Error;
+use heap::{Trace, Tracer};
+use scope::ScopeRef;
+
+// A module defined with `define-module`: the scope its body was evaluated
+// in, and the names in it that importers may see.
+#[derive(Debug)]
+pub struct Module {
+    pub scope: ScopeRef,
+    pub exports: Vec<String>
+}
+
+// Every module defined so far, by name, so that each is only evaluated once,
+// and where to look for the files of those that aren't.
+#[derive(Debug)]
+pub struct Modules {
+    modules: HashMap<Vec<String>, Module>,
+    search_path: Vec<PathBuf>
+}
+
+impl Modules {
+    pub fn new(search_path: Vec<PathBuf>) -> Modules {
+        Modules { modules: HashMap::new(), search_path }
+    }
+
+    pub fn get(&self, name: &[String]) -> Option<&Module> {
+        self.modules.get(name)
+    }
+
+    pub fn define(&mut self, name: Vec<String>, module: Module) {
+        self.modules.insert(name, module);
+    }
+
+    // What each module exports, by the module's name.
+    pub fn exports(&self) -> HashMap<Vec<String>, Vec<String>> {
+        self.modules.iter().map(|(name, module)| (name.clone(), module.exports.clone())).collect()
+    }
+
+    // Finds a file by a path relative to one of the search path directories,
+    // trying each in turn. Absolute paths are used as they are.
+    pub fn find<F>(&self, path: &Path, exists: F) -> Option<PathBuf>
+        where F: Fn(&Path) -> bool {
+        if path.is_absolute() {
+            return Some(path.to_path_buf()).filter(|path| exists(path))
+        }
+        self.search_path.iter()
+            .map(|directory| directory.join(path))
+            .find(|path| exists(path))
+    }
+}
+
+impl Trace for Modules {
+    fn trace(&self, tracer: &mut Tracer) {
+        for module in self.modules.values() {
+            tracer.scope(module.scope);
+        }
+    }
+}
+
+// Where the file defining a module is expected, relative to the search path:
+// (utils list) is defined in utils/list.lithp.
+pub fn module_path(name: &[String]) -> PathBuf {
+    let mut path: PathBuf = name.iter().collect();
+    path.set_extension("lithp");
+    path
+}
+
+// A module name is a list of identifiers, such as (utils list).
+pub fn module_name(atom: &Atom) -> Result<Vec<String>, Error> {
+    match *atom {
+        Atom::List(ref parts) if !parts.is_empty() => parts.iter().map(|part| match *part {
+            Atom::Identifier(ref part) => Ok(part.clone()),
+            _ => Err(Error::new("module names must be lists of identifiers", vec![atom.clone()]))
+        }).collect(),
+        _ => Err(Error::new("module names must be lists of identifiers", vec![atom.clone()]))
+    }
+}
+
+pub fn name_atom(name: &[String]) -> Atom {
+    Atom::List(name.iter().cloned().map(Atom::Identifier).collect())
+}
+
+// What an `import` form imports from one module, and under which names.
+#[derive(Debug, Clone)]
+pub enum ImportSet {
+    Module(Vec<String>),
+    // (only set name...)
+    Only(Box<ImportSet>, Vec<String>),
+    // (except set name...)
+    Except(Box<ImportSet>, Vec<String>),
+    // (prefix set prefix)
+    Prefix(Box<ImportSet>, String),
+    // (rename set (from to)...)
+    Rename(Box<ImportSet>, Vec<(String, String)>)
+}
+
+impl ImportSet {
+    pub fn parse(atom: &Atom) -> Result<ImportSet, Error> {
+        let invalid = || Error::new("invalid import set", vec![atom.clone()]);
+        let (kind, set, rest) = match *atom {
+            Atom::List(ref parts) => match parts.as_slice() {
+                [Atom::Identifier(kind), set, rest @ ..]
+                    if ["only", "except", "prefix", "rename"].contains(&kind.as_str()) => {
+                    (kind.as_str(), Box::new(ImportSet::parse(set)?), rest)
+                },
+                _ => return module_name(atom).map(ImportSet::Module)
+            },
+            _ => return Err(invalid())
+        };
+        let names = || rest.iter().map(|name| match *name {
+            Atom::Identifier(ref name) => Ok(name.clone()),
+            _ => Err(invalid())
+        }).collect::<Result<Vec<String>, Error>>();
+        match kind {
+            "only" => Ok(ImportSet::Only(set, names()?)),
+            "except" => Ok(ImportSet::Except(set, names()?)),
+            "prefix" => match names()?.as_slice() {
+                [prefix] => Ok(ImportSet::Prefix(set, prefix.clone())),
+                _ => Err(invalid())
+            },
+            _ => {
+                let renames = rest.iter().map(|rename| match *rename {
+                    Atom::List(ref pair) => match pair.as_slice() {
+                        [Atom::Identifier(from), Atom::Identifier(to)] => Ok((from.clone(), to.clone())),
+                        _ => Err(invalid())
+                    },
+                    _ => Err(invalid())
+                }).collect::<Result<_, _>>()?;
+                Ok(ImportSet::Rename(set, renames))
+            }
+        }
+    }
+
+    pub fn module(&self) -> &[String] {
+        match *self {
+            ImportSet::Module(ref name) => name,
+            ImportSet::Only(ref set, _) | ImportSet::Except(ref set, _) |
+            ImportSet::Prefix(ref set, _) | ImportSet::Rename(ref set, _) => set.module()
+        }
+    }
+
+    // The names a module's exports are imported as, paired with the names
+    // they are exported as.
+    pub fn names(&self, exports: &[String]) -> Result<Vec<(String, String)>, Error> {
+        match *self {
+            ImportSet::Module(_) => Ok(exports.iter().map(|name| (name.clone(), name.clone())).collect()),
+            ImportSet::Only(ref set, ref only) => {
+                let names = exported(set.names(exports)?, only)?;
+                Ok(names.into_iter().filter(|(to, _)| only.contains(to)).collect())
+            },
+            ImportSet::Except(ref set, ref except) => {
+                let names = exported(set.names(exports)?, except)?;
+                Ok(names.into_iter().filter(|(to, _)| !except.contains(to)).collect())
+            },
+            ImportSet::Prefix(ref set, ref prefix) => {
+                Ok(set.names(exports)?.into_iter().map(|(to, from)| (format!("{}{}", prefix, to), from)).collect())
+            },
+            ImportSet::Rename(ref set, ref renames) => {
+                let mut names = set.names(exports)?;
+                for (from, to) in renames {
+                    match names.iter_mut().find(|(name, _)| name == from) {
+                        Some(name) => name.0 = to.clone(),
+                        None => return Err(not_exported(from))
+                    }
+                }
+                Ok(names)
+            }
+        }
+    }
+}
+
+// Checks that every name listed is among those being imported.
+fn exported(names: Vec<(String, String)>, listed: &[String]) -> Result<Vec<(String, String)>, Error> {
+    match listed.iter().find(|name| !names.iter().any(|(to, _)| to == *name)) {
+        Some(name) => Err(not_exported(name)),
+        None => Ok(names)
+    }
+}
+
+fn not_exported(name: &str) -> Error {
+    Error::new("import of a name the module doesn't export", vec![Atom::Identifier(name.to_string())])
+}
@@ -1,123 +1,236 @@
-use std::fmt;
-use std::fmt::{Display, Formatter, Write};
-use tokenizer::Token;
+use std::mem;
+use std::rc::Rc;
+use tokenizer::{scan, Token};
 use atom::Atom;
-use util::prepend;
+use diagnostic::{Diagnostic, Position};
 
-const ATOM_COUNT_ESTIMATE: usize = 10;
+// How deeply lists and quotes may nest unless configured otherwise. Atoms
+// nested much deeper than this are too deep for the recursion in printing
+// and dropping them.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
 
-#[derive(Debug)]
-pub struct Parser {
-    tokens: Vec<Token>,
-    is_quoted: bool,
+// Where a datum was read from, with the spans of its elements if it is a
+// list, so that where each part of a program was written can be found.
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub position: Position,
+    pub elements: Vec<Span>
 }
 
-impl Display for Atom {
-    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
-        match self {
-            &Atom::List(ref atoms) => {
-                try!(fmt.write_str("( "));
-                for atom in atoms {
-                    try!(atom.fmt(fmt));
-                    try!(fmt.write_char(' '));
-                }
-                try!(fmt.write_str(")"));
-                Ok(())
-            },
-            &Atom::Lambda(_) => fmt.write_str("<lambda>"),
-            &Atom::Identifier(ref name) => name.fmt(fmt),
-            &Atom::Integer(num) => num.fmt(fmt),
-            &Atom::Quoted(ref atom) => {
-                try!(fmt.write_str("' "));
-                atom.fmt(fmt)
-            },
-        }
+impl Span {
+    fn at(position: Position) -> Span {
+        Span { position, elements: vec![] }
     }
 }
 
+#[derive(Debug)]
+pub struct Parser {
+    tokens: Vec<Token>,
+    max_depth: usize
+}
+
 type ParseResult = Result<Atom, &'static str>;
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
-        Parser {
-            tokens: tokens,
-            is_quoted: false
-        }
+        Parser { tokens, max_depth: DEFAULT_MAX_DEPTH }
     }
 
-    pub fn parse(&mut self) -> Result<Atom, &'static str> {
-        match self.parse_atoms() {
-            Ok(value) => {
-                if self.tokens.len() > 0 {
-                    Err("Syntax Error: left over tokens")
-                } else {
-                    Ok(value)
-                }
-            },
-            other => other
-        }
+    pub fn max_depth(mut self, max_depth: usize) -> Parser {
+        self.max_depth = max_depth;
+        self
     }
 
-    fn parse_atoms(&mut self) -> ParseResult {
-        if self.tokens.len() == 0 {
-            // Atoms ->
-            return Ok(Atom::List(Vec::with_capacity(ATOM_COUNT_ESTIMATE)))
+    // Parses the next datum, or returns None if there are no tokens left.
+    pub fn parse_datum(&mut self) -> Result<Option<Atom>, &'static str> {
+        match self.head_token() {
+            None => Ok(None),
+            Some(Token::CloseParen) => Err("unexpected )"),
+            Some(_) => self.parse_atom().map(Some)
         }
-        
-        let first_token = self.tokens[self.tokens.len() - 1].clone();
+    }
 
-        match first_token {
-            Token::CloseParen => {
-                // Atoms ->
-                Ok(Atom::List(Vec::with_capacity(ATOM_COUNT_ESTIMATE)))
-            },
-            _ => {
-                // Atoms -> Atom Atoms
-                let atom = try!(self.parse_atom());
-
-                let atoms = try!(self.parse_atoms());
-                let mut atoms_vec = match atoms {
-                    Atom::List(val) => val,
-                    _ => unreachable!()
-                };
-                Ok(Atom::List(prepend(atom, &mut atoms_vec)))
+    fn parse_atom(&mut self) -> ParseResult {
+        let mut partial = Partial::new(self.max_depth);
+        loop {
+            let token = match self.tokens.pop() {
+                Some(token) => token,
+                None => return Err(partial.end_of_input())
+            };
+            if let Some((datum, _)) = partial.push(token, Position::start())? {
+                return Ok(datum)
             }
         }
     }
 
-    fn parse_atom(&mut self) -> ParseResult {
-        match self.tokens.pop() {
-            Some(Token::Quote) | None => {
-                let atom = try!(self.parse_atom());
-                Ok(Atom::Quoted(Box::new(atom)))
+    fn head_token(&self) -> Option<Token> {
+        self.tokens.last().cloned()
+    }
+}
+
+// A datum being parsed a token at a time, kept as an explicit stack of the
+// lists it is inside so that neither long nor deeply nested lists use up the
+// native stack.
+#[derive(Debug)]
+pub struct Partial {
+    // Lists opened but not yet closed, each with where the quotes before it
+    // were and the spans of what it holds so far.
+    open: Vec<(Vec<Position>, Vec<Atom>, Span)>,
+    // Where the quotes waiting for the next datum are.
+    quotes: Vec<Position>,
+    depth: usize,
+    max_depth: usize
+}
+
+impl Partial {
+    pub fn new(max_depth: usize) -> Partial {
+        Partial { open: vec![], quotes: vec![], depth: 0, max_depth }
+    }
+
+    // Adds the next token, read at `position`, returning the datum and its
+    // span if it is now complete.
+    pub fn push(&mut self, token: Token, position: Position) -> Result<Option<(Atom, Span)>, &'static str> {
+        let (datum, span) = match token {
+            Token::Quote => {
+                self.deeper()?;
+                self.quotes.push(position);
+                return Ok(None)
+            },
+            Token::OpenParen => {
+                self.deeper()?;
+                self.open.push((mem::take(&mut self.quotes), vec![], Span::at(position)));
+                return Ok(None)
             },
-            Some(Token::OpenParen) => self.parse_list(),
-            Some(Token::Identifier(name)) => Ok(Atom::Identifier(name)),
-            Some(Token::Integer(number)) => Ok(Atom::Integer(number)),
-            _ => Err("unexpected token in parse_atom")
+            Token::CloseParen if !self.quotes.is_empty() => return Err("quote must be followed by a datum"),
+            Token::CloseParen => match self.open.pop() {
+                Some((quotes, atoms, span)) => {
+                    self.quotes = quotes;
+                    self.depth -= 1;
+                    (Atom::List(atoms), span)
+                },
+                None => return Err("unexpected )")
+            },
+            Token::Identifier(name) => (Atom::Identifier(name), Span::at(position)),
+            Token::Integer(number) => (Atom::Integer(number), Span::at(position)),
+            Token::String(string) => (Atom::String(Rc::from(string)), Span::at(position))
+        };
+        let datum = quote(datum, self.drop_quotes().len());
+        match self.open.last_mut() {
+            Some((_, atoms, list)) => {
+                atoms.push(datum);
+                list.elements.push(span);
+                Ok(None)
+            },
+            None => Ok(Some((datum, span)))
         }
     }
 
-    fn parse_list(&mut self) -> ParseResult {
-        let body = try!(self.parse_list_body());
-        if self.tokens.pop() != Some(Token::CloseParen) {
-            return Err("list did not end with )")
+    // Whether no datum has been started.
+    pub fn is_empty(&self) -> bool {
+        self.open.is_empty() && self.quotes.is_empty()
+    }
+
+    // The error for input ending with this datum incomplete.
+    pub fn end_of_input(&self) -> &'static str {
+        if self.quotes.is_empty() {
+            "unexpected end of input"
+        } else {
+            "quote at end of input"
         }
-        Ok(body)
     }
 
-    fn parse_list_body(&mut self) -> ParseResult {
-        match self.head_token() {
-            Some(_) => self.parse_atoms(),
-            None => Err("empty list body")
+    // Forgets the quotes waiting for the next datum, returning where they
+    // were.
+    pub fn drop_quotes(&mut self) -> Vec<Position> {
+        self.depth -= self.quotes.len();
+        mem::take(&mut self.quotes)
+    }
+
+    // Where the innermost list still open starts.
+    pub fn innermost(&self) -> Option<Position> {
+        self.open.last().map(|(_, _, span)| span.position)
+    }
+
+    pub fn clear(&mut self) {
+        self.open.clear();
+        self.quotes.clear();
+        self.depth = 0;
+    }
+
+    fn deeper(&mut self) -> Result<(), &'static str> {
+        if self.depth >= self.max_depth {
+            return Err("maximum nesting depth exceeded")
         }
+        self.depth += 1;
+        Ok(())
     }
+}
 
-    fn head_token(&self) -> Option<Token> {
-        if self.tokens.is_empty() {
-            None
-        } else {
-            Some(self.tokens[self.tokens.len() - 1].clone())
+// A whole program, parsed as far as it could be, and what was wrong with it.
+pub struct Parsed {
+    pub program: Atom,
+    // The span of each top level form in the program.
+    pub spans: Vec<Span>,
+    pub diagnostics: Vec<Diagnostic>
+}
+
+// Parses a whole program, carrying on past problems so that all of them are
+// reported at once. A stray ) is skipped, a quote with nothing after it is
+// dropped, lists nested too deeply are left out, and lists left open at the
+// end are closed there, so what was read is still usable by tools.
+pub fn parse_program(source: &str, max_depth: usize) -> Parsed {
+    let (tokens, mut diagnostics) = scan(source);
+    let mut partial = Partial::new(max_depth);
+    let mut program = vec![];
+    let mut spans = vec![];
+    // How many lists deep into a list that is too deep the parser is.
+    let mut skipped = 0;
+    let mut report = |position, message| diagnostics.push(Diagnostic { position, message });
+    for (token, position) in tokens {
+        if skipped > 0 {
+            match token {
+                Token::OpenParen => skipped += 1,
+                Token::CloseParen => skipped -= 1,
+                _ => ()
+            }
+            continue;
+        }
+        if token == Token::CloseParen {
+            for quote in partial.drop_quotes() {
+                report(quote, "quote must be followed by a datum");
+            }
+        }
+        let opens_list = token == Token::OpenParen;
+        match partial.push(token, position) {
+            Ok(Some((datum, span))) => {
+                program.push(datum);
+                spans.push(span);
+            },
+            Ok(None) => (),
+            Err(message) => {
+                report(position, message);
+                // A list that is too deep is left out up to its ).
+                if opens_list {
+                    skipped = 1;
+                }
+            }
+        }
+    }
+    for quote in partial.drop_quotes() {
+        report(quote, "quote at end of input");
+    }
+    while let Some(position) = partial.innermost() {
+        report(position, "unmatched (");
+        let closed = partial.push(Token::CloseParen, position).expect("a list is open with no quotes waiting");
+        if let Some((datum, span)) = closed {
+            program.push(datum);
+            spans.push(span);
         }
     }
-}
\ No newline at end of file
+    diagnostics.sort_by_key(|diagnostic| diagnostic.position);
+    Parsed { program: Atom::List(program), spans, diagnostics }
+}
+
+fn quote(datum: Atom, quotes: usize) -> Atom {
+    (0..quotes).fold(datum, |datum, _| Atom::Quoted(Box::new(datum)))
+}
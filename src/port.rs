@@ -0,0 +1,108 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+use reader::Reader;
+
+// Everything lithp code can do to the world outside the interpreter goes
+// through this, so that an embedder can redirect it, or refuse it to code it
+// doesn't trust by returning errors.
+pub trait Io {
+    // Writes to the console, where output goes unless it is redirected.
+    fn write(&mut self, text: &str) -> io::Result<()>;
+    // The console, which `read` and `read-line` read from unless given a
+    // port. Only opened once.
+    fn stdin(&mut self) -> io::Result<Box<dyn BufRead>>;
+    fn open_input(&mut self, path: &Path) -> io::Result<Box<dyn BufRead>>;
+    // Creates a file to write to, replacing any already there.
+    fn open_output(&mut self, path: &Path) -> io::Result<Box<dyn Write>>;
+    fn exists(&self, path: &Path) -> bool;
+}
+
+// The process's standard input and output, and the file system.
+pub struct StdIo;
+
+impl Io for StdIo {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        io::stdout().write_all(text.as_bytes())
+    }
+
+    fn stdin(&mut self) -> io::Result<Box<dyn BufRead>> {
+        Ok(Box::new(BufReader::new(io::stdin())))
+    }
+
+    fn open_input(&mut self, path: &Path) -> io::Result<Box<dyn BufRead>> {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+
+    fn open_output(&mut self, path: &Path) -> io::Result<Box<dyn Write>> {
+        Ok(Box::new(BufWriter::new(File::create(path)?)))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+}
+
+pub type PortRef = Rc<RefCell<Port>>;
+
+// Something lithp code reads from or writes to.
+pub enum Port {
+    Input(Reader<Box<dyn BufRead>>),
+    Output(Box<dyn Write>),
+    // Collects what is written to it, for `with-output-to-string`.
+    StringOutput(String),
+    Closed { input: bool }
+}
+
+impl Port {
+    pub fn new(port: Port) -> PortRef {
+        Rc::new(RefCell::new(port))
+    }
+
+    pub fn is_input(&self) -> bool {
+        matches!(*self, Port::Input(_) | Port::Closed { input: true })
+    }
+
+    pub fn write(&mut self, text: &str) -> Result<(), &'static str> {
+        match *self {
+            Port::Output(ref mut output) => output.write_all(text.as_bytes()).map_err(|_| "error writing to port"),
+            Port::StringOutput(ref mut output) => {
+                output.push_str(text);
+                Ok(())
+            },
+            Port::Input(_) => Err("cannot write to an input port"),
+            Port::Closed { .. } => Err("port is closed")
+        }
+    }
+
+    pub fn reader(&mut self) -> Result<&mut Reader<Box<dyn BufRead>>, &'static str> {
+        match *self {
+            Port::Input(ref mut reader) => Ok(reader),
+            Port::Closed { .. } => Err("port is closed"),
+            _ => Err("cannot read from an output port")
+        }
+    }
+
+    // Closing a port flushes anything written to it. Closing it again does
+    // nothing.
+    pub fn close(&mut self) -> Result<(), &'static str> {
+        if let Port::Output(ref mut output) = *self {
+            output.flush().map_err(|_| "error writing to port")?;
+        }
+        *self = Port::Closed { input: self.is_input() };
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Port {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if self.is_input() {
+            fmt.write_str("#<input port>")
+        } else {
+            fmt.write_str("#<output port>")
+        }
+    }
+}
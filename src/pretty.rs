@@ -0,0 +1,372 @@
+use atom::Atom;
+
+pub const DEFAULT_WIDTH: usize = 80;
+
+// A document describing the ways an atom may be laid out, after Wadler's "A
+// prettier printer". Each group is printed on one line if it fits in what is
+// left of the line, and otherwise has every line break in it taken.
+pub enum Doc {
+    Text(String),
+    // A space when its group is flat, otherwise a new line.
+    Line,
+    // A new line even in a group that would otherwise be flat.
+    HardLine,
+    // An empty line, put before the line break that follows it.
+    BlankLine,
+    // Nothing, but keeps any group around it from being flat, as after a
+    // comment that runs to the end of its line.
+    BreakParent,
+    Concat(Vec<Doc>),
+    // Indents new lines by this much more than the enclosing indentation.
+    Nest(usize, Box<Doc>),
+    // Indents new lines to the column the document starts at.
+    Align(Box<Doc>),
+    Group(Box<Doc>)
+}
+
+// One element of a list or program being laid out, along with the comments
+// and empty lines around it in the source it came from, if any.
+pub struct Element {
+    pub doc: Doc,
+    // The identifier the element is, if it is one, which decides how a list
+    // starting with it is indented.
+    pub name: Option<String>,
+    pub is_list: bool,
+    // Comments on lines of their own before the element, each with whether
+    // an empty line came before it.
+    pub comments_before: Vec<(bool, String)>,
+    // Whether an empty line came right before the element itself.
+    pub blank_before: bool,
+    // A comment after the element on the same line.
+    pub comment_after: Option<String>,
+    // Whether the element is a comment, as when comments are the last thing
+    // in a list.
+    pub is_comment: bool
+}
+
+impl Element {
+    pub fn new(doc: Doc, name: Option<String>, is_list: bool) -> Element {
+        Element {
+            doc,
+            name,
+            is_list,
+            comments_before: Vec::new(),
+            blank_before: false,
+            comment_after: None,
+            is_comment: false
+        }
+    }
+
+    pub fn comment(text: String) -> Element {
+        let mut element = Element::new(Doc::Text(text), None, false);
+        element.is_comment = true;
+        element
+    }
+
+    fn ends_with_comment(&self) -> bool {
+        self.is_comment || self.comment_after.is_some()
+    }
+
+    fn has_comments(&self) -> bool {
+        !self.comments_before.is_empty() || self.blank_before || self.ends_with_comment()
+    }
+}
+
+// Forms whose first few arguments stay on the line with the form's name, with
+// the rest as a body indented under it, paired with how many arguments that is.
+const BODY_FORMS: [(&str, usize); 9] = [("define", 1), ("lambda", 1), ("let", 1), ("let*", 1),
+                                        ("letrec", 1), ("letrec*", 1), ("do", 2), ("guard", 1),
+                                        ("dynamic-wind", 0)];
+
+const BODY_INDENT: usize = 2;
+
+// Lays out an atom in its `write` form, breaking lists across lines and
+// indenting them by the usual Lisp conventions so that they fit in `width`
+// columns where possible.
+pub fn pretty(atom: &Atom, width: usize) -> String {
+    layout(&to_doc(atom), width)
+}
+
+fn to_doc(atom: &Atom) -> Doc {
+    match *atom {
+        Atom::List(ref atoms) | Atom::Form(_, ref atoms) => list(atoms.iter().map(to_element).collect()),
+        Atom::Quoted(ref atom) => Doc::Concat(vec![Doc::Text("'".to_string()), to_doc(atom)]),
+        ref atom => Doc::Text(atom.to_string())
+    }
+}
+
+fn to_element(atom: &Atom) -> Element {
+    let name = match *atom {
+        Atom::Identifier(ref name) => Some(name.clone()),
+        _ => None
+    };
+    Element::new(to_doc(atom), name, is_list(atom))
+}
+
+fn is_list(atom: &Atom) -> bool {
+    match *atom {
+        Atom::List(_) | Atom::Form(..) => true,
+        Atom::Quoted(ref atom) => is_list(atom),
+        _ => false
+    }
+}
+
+// A list of elements, indented as a form if it starts with an identifier and
+// as data otherwise.
+pub fn list(elements: Vec<Element>) -> Doc {
+    let head_name = match elements.first() {
+        Some(head) if elements.len() > 1 && !head.has_comments() => head.name.clone(),
+        _ => None
+    };
+    let name = match head_name {
+        Some(name) => name,
+        // Data with nothing nested is filled onto as few lines as it takes.
+        None if elements.iter().all(|element| !element.is_list && !element.has_comments()) => {
+            return parenthesize(vec![Doc::Align(Box::new(fill(elements)))], false)
+        },
+        // Other data, or a call with no arguments, has every element aligned.
+        None => return aligned(elements)
+    };
+
+    let distinguished = BODY_FORMS.iter()
+        .find(|&&(form, _)| form == name)
+        .map(|&(_, count)| count);
+    let mut args = elements;
+    let head = args.remove(0);
+    let ends_with_comment = args.last().is_some_and(Element::ends_with_comment);
+    match distinguished {
+        Some(count) => {
+            // A named let has its name before the bindings.
+            let count = match (name.as_str(), args.first()) {
+                ("let", Some(first)) if first.name.is_some() => count + 1,
+                _ => count
+            };
+            let count = count.min(args.len());
+            // Only the last of the arguments kept with the name may have a
+            // comment, after it, as anything else would run into the next.
+            let commented = args[..count].iter().enumerate().any(|(i, arg)| {
+                !arg.comments_before.is_empty() || arg.blank_before || arg.is_comment ||
+                    (arg.comment_after.is_some() && i + 1 < count)
+            });
+            if commented {
+                args.insert(0, head);
+                return aligned(args)
+            }
+            let body = args.split_off(count);
+            let mut docs = vec![head.doc];
+            for arg in args {
+                docs.push(Doc::Text(" ".to_string()));
+                docs.push(element_doc(arg));
+            }
+            if !body.is_empty() {
+                let body = Doc::Concat(vec![separator(&body[0]), lines(body)]);
+                docs.push(Doc::Nest(BODY_INDENT, Box::new(body)));
+            }
+            parenthesize(docs, ends_with_comment)
+        },
+        // A call has its arguments aligned under the first.
+        None => parenthesize(vec![
+            head.doc,
+            Doc::Text(" ".to_string()),
+            Doc::Align(Box::new(lines(args)))
+        ], ends_with_comment)
+    }
+}
+
+fn aligned(elements: Vec<Element>) -> Doc {
+    let ends_with_comment = elements.last().is_some_and(Element::ends_with_comment);
+    parenthesize(vec![Doc::Align(Box::new(lines(elements)))], ends_with_comment)
+}
+
+// Wraps the contents of a list in parentheses, putting the closing one on a
+// line of its own when a comment would otherwise run into it.
+fn parenthesize(mut docs: Vec<Doc>, ends_with_comment: bool) -> Doc {
+    docs.insert(0, Doc::Text("(".to_string()));
+    if ends_with_comment {
+        docs.push(Doc::HardLine);
+    }
+    docs.push(Doc::Text(")".to_string()));
+    Doc::Group(Box::new(Doc::Align(Box::new(Doc::Concat(docs)))))
+}
+
+// The top level of a source file, with every element on lines of its own.
+pub fn program(elements: Vec<Element>) -> Doc {
+    let mut docs = Vec::with_capacity(elements.len() * 2);
+    for (i, element) in elements.into_iter().enumerate() {
+        if i > 0 {
+            if first_blank(&element) {
+                docs.push(Doc::BlankLine);
+            }
+            docs.push(Doc::HardLine);
+        }
+        docs.push(element_doc(element));
+    }
+    Doc::Concat(docs)
+}
+
+fn lines(elements: Vec<Element>) -> Doc {
+    let mut docs = Vec::with_capacity(elements.len() * 2);
+    for (i, element) in elements.into_iter().enumerate() {
+        if i > 0 {
+            docs.push(separator(&element));
+        }
+        docs.push(element_doc(element));
+    }
+    Doc::Concat(docs)
+}
+
+// Like `lines`, except each line break is only taken when the element after
+// it doesn't fit on the current line.
+fn fill(elements: Vec<Element>) -> Doc {
+    let mut docs = Vec::with_capacity(elements.len());
+    for (i, element) in elements.into_iter().enumerate() {
+        if i == 0 {
+            docs.push(element.doc);
+        } else {
+            docs.push(Doc::Group(Box::new(Doc::Concat(vec![Doc::Line, element.doc]))));
+        }
+    }
+    Doc::Concat(docs)
+}
+
+// The line break before an element, keeping an empty line from the source.
+fn separator(element: &Element) -> Doc {
+    if first_blank(element) {
+        Doc::Concat(vec![Doc::BlankLine, Doc::Line])
+    } else {
+        Doc::Line
+    }
+}
+
+// Whether an empty line came before the element or its first comment.
+fn first_blank(element: &Element) -> bool {
+    element.comments_before.first().map_or(element.blank_before, |&(blank, _)| blank)
+}
+
+fn element_doc(element: Element) -> Doc {
+    if !element.has_comments() {
+        return element.doc
+    }
+    let mut docs = Vec::new();
+    let had_comments = !element.comments_before.is_empty();
+    for (i, (blank, comment)) in element.comments_before.into_iter().enumerate() {
+        if i > 0 {
+            if blank {
+                docs.push(Doc::BlankLine);
+            }
+            docs.push(Doc::HardLine);
+        }
+        docs.push(Doc::Text(comment));
+    }
+    if had_comments {
+        if element.blank_before {
+            docs.push(Doc::BlankLine);
+        }
+        docs.push(Doc::HardLine);
+    }
+    docs.push(element.doc);
+    if let Some(comment) = element.comment_after {
+        docs.push(Doc::Text(" ".to_string()));
+        docs.push(Doc::Text(comment));
+        docs.push(Doc::BreakParent);
+    }
+    if element.is_comment {
+        docs.push(Doc::BreakParent);
+    }
+    Doc::Concat(docs)
+}
+
+pub fn layout(doc: &Doc, width: usize) -> String {
+    let mut output = String::new();
+    let mut column = 0;
+    // Documents still to be laid out, last first, with their indentation and
+    // whether they are in a group being printed flat.
+    let mut pending = vec![(0, false, doc)];
+    while let Some((indent, flat, doc)) = pending.pop() {
+        match *doc {
+            Doc::Text(ref text) => {
+                output.push_str(text);
+                column += text.chars().count();
+            },
+            Doc::Line if flat => {
+                output.push(' ');
+                column += 1;
+            },
+            Doc::Line | Doc::HardLine => {
+                output.push('\n');
+                output.extend((0..indent).map(|_| ' '));
+                column = indent;
+            },
+            Doc::BlankLine => output.push('\n'),
+            Doc::BreakParent => (),
+            Doc::Concat(ref docs) => {
+                pending.extend(docs.iter().rev().map(|doc| (indent, flat, doc)));
+            },
+            Doc::Nest(nesting, ref doc) => pending.push((indent + nesting, flat, doc)),
+            Doc::Align(ref doc) => pending.push((column, flat, doc)),
+            Doc::Group(ref doc) => {
+                let flat = flat || fits(width.saturating_sub(column), doc, &pending);
+                pending.push((indent, flat, doc));
+            }
+        }
+    }
+    output
+}
+
+// Whether `doc` printed flat, followed by whatever comes after it up to the
+// next line break, fits in `remaining` columns.
+fn fits(remaining: usize, doc: &Doc, rest: &[(usize, bool, &Doc)]) -> bool {
+    let mut remaining = remaining as isize;
+    let mut flat_docs = vec![doc];
+    let mut rest = rest.iter().rev();
+    loop {
+        let (doc, flat) = match flat_docs.pop() {
+            Some(doc) => (doc, true),
+            None => match rest.next() {
+                Some(&(_, flat, doc)) => (doc, flat),
+                None => return true
+            }
+        };
+        match *doc {
+            Doc::Text(ref text) => {
+                remaining -= text.chars().count() as isize;
+                if remaining < 0 { return false }
+            },
+            Doc::Line if flat => {
+                remaining -= 1;
+                if remaining < 0 { return false }
+            },
+            Doc::HardLine | Doc::BlankLine | Doc::BreakParent if flat => return false,
+            Doc::Line | Doc::HardLine | Doc::BlankLine => return true,
+            Doc::Concat(ref docs) if flat => flat_docs.extend(docs.iter().rev()),
+            Doc::Nest(_, ref doc) | Doc::Align(ref doc) | Doc::Group(ref doc) if flat => {
+                flat_docs.push(doc)
+            },
+            // Anything else after the group is printed broken, so only the
+            // text before its first line break matters.
+            _ => if let Some(fits) = first_line_fits(&mut remaining, doc) {
+                return fits
+            }
+        }
+    }
+}
+
+// Takes the width of the text before the first line break in `doc` from
+// `remaining`, returning whether it fits once a line break is reached, or
+// None if `doc` has no line break.
+fn first_line_fits(remaining: &mut isize, doc: &Doc) -> Option<bool> {
+    let mut docs = vec![doc];
+    while let Some(doc) = docs.pop() {
+        match *doc {
+            Doc::Text(ref text) => {
+                *remaining -= text.chars().count() as isize;
+                if *remaining < 0 { return Some(false) }
+            },
+            Doc::Line | Doc::HardLine | Doc::BlankLine => return Some(true),
+            Doc::BreakParent => (),
+            Doc::Concat(ref inner) => docs.extend(inner.iter().rev()),
+            Doc::Nest(_, ref doc) | Doc::Align(ref doc) | Doc::Group(ref doc) => docs.push(doc)
+        }
+    }
+    None
+}
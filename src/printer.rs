@@ -0,0 +1,82 @@
+use std::fmt;
+use std::fmt::{Display, Formatter, Write};
+use atom::Atom;
+
+// Atoms display in their `write` form, which the parser reads back as an
+// equal atom. Procedures, continuations, conditions and ports are written as
+// #<...>, which the parser refuses to read rather than misreading.
+impl Display for Atom {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        print(self, fmt, false)
+    }
+}
+
+// The human friendly form of an atom, used by `display`, in which strings
+// and identifiers appear as their contents with nothing escaped.
+pub struct Displayed<'a>(pub &'a Atom);
+
+impl<'a> Display for Displayed<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        print(self.0, fmt, true)
+    }
+}
+
+fn print(atom: &Atom, fmt: &mut Formatter, human: bool) -> Result<(), fmt::Error> {
+    match *atom {
+        Atom::List(ref atoms) | Atom::Form(_, ref atoms) => {
+            fmt.write_char('(')?;
+            for (i, atom) in atoms.iter().enumerate() {
+                if i > 0 {
+                    fmt.write_char(' ')?;
+                }
+                print(atom, fmt, human)?;
+            }
+            fmt.write_char(')')
+        },
+        Atom::Integer(num) => num.fmt(fmt),
+        Atom::Identifier(ref name) if human || !needs_bars(name) => fmt.write_str(name),
+        Atom::Identifier(ref name) => print_escaped(name, '|', fmt),
+        Atom::String(ref string) if human => fmt.write_str(string),
+        Atom::String(ref string) => print_escaped(string, '"', fmt),
+        Atom::Quoted(ref atom) => {
+            fmt.write_char('\'')?;
+            print(atom, fmt, human)
+        },
+        Atom::Builtin(name) => write!(fmt, "#<procedure {}>", name),
+        Atom::Lambda(ref closure) => match closure.name {
+            Some(ref name) => write!(fmt, "#<lambda {}>", name),
+            None => fmt.write_str("#<lambda>")
+        },
+        Atom::Continuation(_) => fmt.write_str("#<continuation>"),
+        Atom::Condition(ref condition) => write!(fmt, "#<condition {}>", condition),
+        Atom::Port(ref port) => write!(fmt, "{:?}", port.borrow())
+    }
+}
+
+// Whether an identifier would read back as something else if written as is,
+// in which case it is written between bars.
+fn needs_bars(name: &str) -> bool {
+    let integer = name.trim_start_matches('-');
+    name.is_empty() ||
+        name.starts_with("#<") ||
+        (!integer.is_empty() && integer.chars().all(|c| c.is_ascii_digit()) && name.len() - integer.len() <= 1) ||
+        name.chars().any(|c| c.is_whitespace() || "()\"';|\\".contains(c))
+}
+
+fn print_escaped(text: &str, delimiter: char, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+    fmt.write_char(delimiter)?;
+    for c in text.chars() {
+        match c {
+            '\\' => fmt.write_str("\\\\")?,
+            '\n' => fmt.write_str("\\n")?,
+            '\t' => fmt.write_str("\\t")?,
+            '\r' => fmt.write_str("\\r")?,
+            c if c == delimiter => {
+                fmt.write_char('\\')?;
+                fmt.write_char(c)?
+            },
+            c => fmt.write_char(c)?
+        }
+    }
+    fmt.write_char(delimiter)
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// How many calls deep a stack written out for flame graphs may be. Time
+// spent deeper down, as in a deep recursion, is counted as part of the call
+// at this depth, as the stacks would otherwise take room quadratic in their
+// depth.
+const MAX_FOLDED_DEPTH: usize = 256;
+
+// What the profiler tells apart: closures by the name they were bound to,
+// or where their body starts if they have none, and built ins by name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Procedure {
+    Closure(Rc<str>),
+    Builtin(&'static str)
+}
+
+impl Display for Procedure {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Procedure::Closure(ref name) => fmt.write_str(name),
+            Procedure::Builtin(name) => write!(fmt, "{} (built in)", name)
+        }
+    }
+}
+
+// What was measured of one procedure. Allocations are of scopes on the heap.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    pub calls: u64,
+    // Time spent in calls, including the calls they made. A recursive call
+    // is counted once, as part of the outermost call.
+    pub inclusive: Duration,
+    // Time spent in calls, leaving out the procedures they called.
+    pub exclusive: Duration,
+    pub allocations: usize
+}
+
+// A call of a closure under way.
+struct Active {
+    procedure: Procedure,
+    // Where on the evaluator's stack its call frame is.
+    index: usize,
+    // Its stack in `nodes`.
+    node: usize,
+    start: Instant,
+    // Heap allocations when it started.
+    allocated: usize,
+    // What the procedures it called took.
+    children: Duration,
+    children_allocated: usize
+}
+
+// Measures every call of a procedure while the interpreter has it. The
+// evaluator tells it when closures are called and return, and how long each
+// built in took; procedures a built in calls are counted as called from the
+// closure that called the built in.
+#[derive(Default)]
+pub struct Profiler {
+    stats: HashMap<Procedure, Stats>,
+    active: Vec<Active>,
+    // How many calls of each procedure are active.
+    depths: HashMap<Procedure, usize>,
+    // Every stack of calls seen, as a tree whose root is index 0, each with
+    // its parent, procedure and the time spent in it but not its children.
+    nodes: Vec<(usize, Option<Procedure>, Duration)>,
+    children: HashMap<(usize, Procedure), usize>
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler { nodes: vec![(0, None, Duration::ZERO)], ..Profiler::default() }
+    }
+
+    // A closure has been called, with its frame at `index` on the stack.
+    pub fn enter(&mut self, procedure: Procedure, index: usize, allocated: usize) {
+        self.stats.entry(procedure.clone()).or_default().calls += 1;
+        *self.depths.entry(procedure.clone()).or_insert(0) += 1;
+        let node = self.node(procedure.clone());
+        self.active.push(Active {
+            procedure,
+            index,
+            node,
+            start: Instant::now(),
+            allocated,
+            children: Duration::ZERO,
+            children_allocated: 0
+        });
+    }
+
+    // The stack is now `len` frames deep, so the calls whose frames were
+    // above that have returned.
+    pub fn leave(&mut self, len: usize, allocated: usize) {
+        while self.active.last().is_some_and(|active| active.index >= len) {
+            let active = self.active.pop().unwrap();
+            let inclusive = active.start.elapsed();
+            let exclusive = inclusive.saturating_sub(active.children);
+            let total_allocated = allocated - active.allocated;
+            let depth = self.depths.get_mut(&active.procedure).unwrap();
+            *depth -= 1;
+            let outermost = *depth == 0;
+            let stats = self.stats.get_mut(&active.procedure).unwrap();
+            if outermost {
+                stats.inclusive += inclusive;
+            }
+            stats.exclusive += exclusive;
+            stats.allocations += total_allocated - active.children_allocated;
+            self.nodes[active.node].2 += exclusive;
+            if let Some(parent) = self.active.last_mut() {
+                parent.children += inclusive;
+                parent.children_allocated += total_allocated;
+            }
+        }
+    }
+
+    // A built in returned after `elapsed`, having been called with `depth`
+    // closure calls active.
+    pub fn built_in(&mut self, name: &'static str, depth: usize, elapsed: Duration, allocated: usize) {
+        // A built in that jumps to a continuation may leave calls it was
+        // called in.
+        let depth = depth.min(self.active.len());
+        let procedure = Procedure::Builtin(name);
+        let stats = self.stats.entry(procedure.clone()).or_default();
+        stats.calls += 1;
+        stats.inclusive += elapsed;
+        stats.exclusive += elapsed;
+        stats.allocations += allocated;
+        let parent = match depth {
+            0 => 0,
+            depth => self.active[depth - 1].node
+        };
+        let node = self.child(parent, procedure);
+        self.nodes[node].2 += elapsed;
+        if depth > 0 {
+            let caller = &mut self.active[depth - 1];
+            caller.children += elapsed;
+            caller.children_allocated += allocated;
+        }
+    }
+
+    // How many closure calls are active.
+    pub fn depth(&self) -> usize {
+        self.active.len()
+    }
+
+    // Every procedure called, the one taking the most time of its own first.
+    pub fn stats(&self) -> Vec<(&Procedure, &Stats)> {
+        let mut stats: Vec<_> = self.stats.iter().collect();
+        stats.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then_with(|| a.0.to_string().cmp(&b.0.to_string())));
+        stats
+    }
+
+    // The time spent in each stack of calls, one stack a line as `a;b;c`
+    // followed by microseconds, the folded format flame graph tools read.
+    pub fn folded(&self) -> String {
+        let count = self.nodes.len();
+        let mut depths = vec![0; count];
+        // The node each node's time is counted in, itself unless it is too
+        // deep.
+        let mut shown: Vec<usize> = (0..count).collect();
+        let mut times = vec![Duration::ZERO; count];
+        let mut paths = vec![String::new(); count];
+        // Parents are always made before their children.
+        for (node, &(parent, ref procedure, time)) in self.nodes.iter().enumerate().skip(1) {
+            depths[node] = depths[parent] + 1;
+            if depths[node] > MAX_FOLDED_DEPTH {
+                shown[node] = shown[parent];
+            } else {
+                let name = match *procedure {
+                    Some(Procedure::Closure(ref name)) => name.replace(';', ":"),
+                    Some(Procedure::Builtin(name)) => name.to_string(),
+                    None => unreachable!()
+                };
+                paths[node] = match parent {
+                    0 => name,
+                    parent => format!("{};{}", paths[parent], name)
+                };
+            }
+            times[shown[node]] += time;
+        }
+        let mut lines: Vec<String> = (1..count)
+            .filter(|&node| shown[node] == node && times[node].as_micros() > 0)
+            .map(|node| format!("{} {}\n", paths[node], times[node].as_micros()))
+            .collect();
+        lines.sort();
+        lines.concat()
+    }
+
+    // The node for calling `procedure` from the innermost active call.
+    fn node(&mut self, procedure: Procedure) -> usize {
+        let parent = self.active.last().map_or(0, |active| active.node);
+        self.child(parent, procedure)
+    }
+
+    fn child(&mut self, parent: usize, procedure: Procedure) -> usize {
+        let next = self.nodes.len();
+        let node = *self.children.entry((parent, procedure.clone())).or_insert(next);
+        if node == next {
+            self.nodes.push((parent, Some(procedure), Duration::ZERO));
+        }
+        node
+    }
+}
+
+// The report `lithp --profile` prints, a line for each procedure, sorted by
+// the time spent in it.
+pub struct Report<'a>(pub &'a Profiler);
+
+impl<'a> Display for Report<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        let stats = self.0.stats();
+        let width = stats.iter().map(|(procedure, _)| procedure.to_string().len()).max().unwrap_or(0).max(9);
+        writeln!(fmt, "{:<width$}  {:>10}  {:>12}  {:>12}  {:>11}",
+                 "procedure", "calls", "inclusive ms", "exclusive ms", "allocations", width = width)?;
+        for (procedure, stats) in stats {
+            writeln!(fmt, "{:<width$}  {:>10}  {:>12.3}  {:>12.3}  {:>11}",
+                     procedure.to_string(), stats.calls, millis(stats.inclusive), millis(stats.exclusive),
+                     stats.allocations, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,161 @@
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::mem;
+use atom::Atom;
+use diagnostic::{Diagnostic, Position};
+use parser::{Partial, Span, DEFAULT_MAX_DEPTH};
+use tokenizer::{scan, Token};
+
+// Reads top level data one at a time from any buffered input, such as
+// standard input or a large data file, pulling in another line only when
+// those read so far don't complete the next datum. Only the datum being read
+// and the rest of its line are held at once.
+pub struct Reader<R> {
+    input: R,
+    // Tokens read but not yet parsed, with where they start, ending with the
+    // first problem on their line if there was one.
+    tokens: VecDeque<Result<(Token, Position), Diagnostic>>,
+    // Lines ending inside a string literal, which can't be tokenized yet.
+    pending: String,
+    // The number of the first line in `pending`, or of the next line read.
+    line: usize,
+    // The last lines tokenized, which the tokens left come from, and the
+    // number of the first of them.
+    text: String,
+    text_line: usize,
+    datum: Partial,
+    // Where the datum being read starts.
+    start: Position
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Reader<R> {
+        Reader {
+            input,
+            tokens: VecDeque::new(),
+            pending: String::new(),
+            line: 1,
+            text: String::new(),
+            text_line: 1,
+            datum: Partial::new(DEFAULT_MAX_DEPTH),
+            start: Position::start()
+        }
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Reader<R> {
+        self.datum = Partial::new(max_depth);
+        self
+    }
+
+    // Returns the next datum, or None at the end of the input. After an
+    // error, the datum it was found in and the rest of its line are skipped.
+    pub fn read(&mut self) -> Result<Option<Atom>, Diagnostic> {
+        Ok(self.read_spanned()?.map(|(datum, _)| datum))
+    }
+
+    // Returns the next datum along with where it and its elements were read.
+    pub fn read_spanned(&mut self) -> Result<Option<(Atom, Span)>, Diagnostic> {
+        loop {
+            while let Some(token) = self.tokens.pop_front() {
+                let (token, position) = match token {
+                    Ok(token) => token,
+                    Err(diagnostic) => return Err(self.fail(diagnostic))
+                };
+                if self.datum.is_empty() {
+                    self.start = position;
+                }
+                match self.datum.push(token, position) {
+                    Ok(Some(datum)) => return Ok(Some(datum)),
+                    Ok(None) => (),
+                    Err(message) => return Err(self.fail(Diagnostic { position, message }))
+                }
+            }
+
+            if !self.scan_line()? {
+                if !self.pending.is_empty() {
+                    let (_, mut diagnostics) = scan(&self.pending);
+                    let mut diagnostic = diagnostics.remove(0);
+                    diagnostic.position = self.offset(diagnostic.position);
+                    self.pending.clear();
+                    return Err(self.fail(diagnostic))
+                }
+                if self.datum.is_empty() {
+                    return Ok(None)
+                }
+                let diagnostic = Diagnostic { position: self.start, message: self.datum.end_of_input() };
+                return Err(self.fail(diagnostic))
+            }
+        }
+    }
+
+    // Returns the rest of the line the last datum read ended on, or the next
+    // line if nothing is left of it, without its line ending. Returns None at
+    // the end of the input.
+    pub fn read_line(&mut self) -> Result<Option<String>, Diagnostic> {
+        self.datum.clear();
+        self.pending.clear();
+        if let Some(Ok((_, position))) = self.tokens.front() {
+            let rest = self.text.lines()
+                .nth(position.line - self.text_line)
+                .map(|line| line.chars().skip(position.column - 1).collect())
+                .unwrap_or_default();
+            self.tokens.clear();
+            return Ok(Some(rest))
+        }
+        self.tokens.clear();
+        let position = Position { line: self.line, column: 1 };
+        let mut line = String::new();
+        let read = self.input.read_line(&mut line)
+            .map_err(|_| Diagnostic { position, message: "error reading input" })?;
+        if read == 0 {
+            return Ok(None)
+        }
+        self.line += 1;
+        let end = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(end);
+        Ok(Some(line))
+    }
+
+    // Reads and tokenizes another line, returning false at the end of the
+    // input.
+    fn scan_line(&mut self) -> Result<bool, Diagnostic> {
+        let position = Position { line: self.line, column: 1 };
+        let read = self.input.read_line(&mut self.pending)
+            .map_err(|_| Diagnostic { position, message: "error reading input" })?;
+        if read == 0 {
+            return Ok(false)
+        }
+        let (tokens, diagnostics) = scan(&self.pending);
+        let error = diagnostics.into_iter().next();
+        // A string literal continuing onto the next line isn't an error yet.
+        if error.as_ref().is_some_and(|error| error.message == "unterminated string literal") {
+            return Ok(true)
+        }
+        let tokens: Vec<_> = tokens.into_iter()
+            .filter(|&(_, position)| error.as_ref().is_none_or(|error| position < error.position))
+            .collect();
+        for (token, position) in tokens {
+            let position = self.offset(position);
+            self.tokens.push_back(Ok((token, position)));
+        }
+        if let Some(mut error) = error {
+            error.position = self.offset(error.position);
+            self.tokens.push_back(Err(error));
+        }
+        self.text_line = self.line;
+        self.line += self.pending.matches('\n').count();
+        self.text = mem::take(&mut self.pending);
+        Ok(true)
+    }
+
+    // Moves a position in `pending` to where `pending` is in the input.
+    fn offset(&self, position: Position) -> Position {
+        Position { line: position.line + self.line - 1, column: position.column }
+    }
+
+    fn fail(&mut self, diagnostic: Diagnostic) -> Diagnostic {
+        self.datum.clear();
+        self.tokens.clear();
+        diagnostic
+    }
+}
@@ -1,46 +1,44 @@
 use std::collections::HashMap;
-use std::fmt::Display;
-use std::cell::RefCell;
-use std::rc::Rc;
 
-
-pub type ScopeRef<V> = Rc<RefCell<Scope<V>>>;   
+// Scopes live in the garbage collected `Heap`, so a reference to one is just
+// an index into it. Walking the parent chain therefore goes through the heap.
+// The slot at an index is reused once its scope is collected, so a reference
+// also has the generation of the slot it was made for, and a reference left
+// over from before is never taken for the scope that replaced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScopeRef {
+    pub index: usize,
+    pub generation: u32
+}
 
 #[derive(Debug, Clone)]
 pub struct Scope<V> {
-    pub parent: Option<Rc<RefCell<Scope<V>>>>,
+    pub parent: Option<ScopeRef>,
     bindings: HashMap<String, V>
 }
 
-impl<V: Display + Clone> Scope<V> {
-    pub fn new() -> Scope<V> {
-        Scope{parent: None, bindings: HashMap::new()}
+impl<V: Clone> Scope<V> {
+    pub fn new(parent: Option<ScopeRef>) -> Scope<V> {
+        Scope{parent, bindings: HashMap::new()}
+    }
+
+    pub fn get_local(&self, name: &str) -> Option<V> {
+        self.bindings.get(name).cloned()
     }
 
-    pub fn get(&self, name: &str) -> Option<V> {
-        match self.bindings.get(name) {
-            Some(value) => Some(value.clone()),
-            None => self.parent.as_ref().and_then(|p| (*p).borrow().get(name))
-        }
+    pub fn has_local(&self, name: &str) -> bool {
+        self.bindings.contains_key(name)
     }
 
     pub fn set_local(&mut self, name: &str, value: V) {
         self.bindings.insert(name.to_string(), value);
     }
 
-    pub fn set_inherited(&mut self, name: &str, value: V) {
-        match self.bindings.get(name) {
-            Some(_) => { self.bindings.insert(name.to_string(), value); },
-            None => { self.parent.as_ref().map(|p| (*p).borrow_mut().set_inherited(name, value)); }
-        }
+    pub fn names(&self) -> Vec<String> {
+        self.bindings.keys().cloned().collect()
     }
-}
 
-pub fn new_scope<V: Display + Clone>() -> ScopeRef<V> {
-    Rc::new(RefCell::new(Scope::new()))
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.bindings.values()
+    }
 }
-
-pub fn new_child_scope<V: Display + Clone>(scope: &ScopeRef<V>) -> ScopeRef<V> {
-    let scope = Scope{parent: Some(scope.clone()), bindings: HashMap::new()};
-    Rc::new(RefCell::new(scope))
-}
\ No newline at end of file
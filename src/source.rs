@@ -0,0 +1,102 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use atom::Atom;
+use diagnostic::Position;
+use parser::Span;
+
+// Where an expression was written: the file or other source it was read
+// from, and its place there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub source: Rc<str>,
+    pub position: Position
+}
+
+impl Display for Location {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}:{}", self.source, self.position)
+    }
+}
+
+// Marks the lists in a form read from `source` that will be evaluated, as
+// calls or special forms, with where they were written, turning them into
+// `Atom::Form`s. The special forms are walked as the evaluator walks them, so
+// that binding lists, parameter lists and quoted data are left as they were.
+pub fn locate(form: Atom, span: &Span, source: &Rc<str>) -> Atom {
+    let atoms = match form {
+        Atom::List(atoms) => atoms,
+        form => return form
+    };
+    let location = Rc::new(Location { source: source.clone(), position: span.position });
+    let keyword = match atoms.first() {
+        Some(Atom::Identifier(name)) => name.clone(),
+        _ => String::new()
+    };
+    let expression = |atom, span: &Span| locate(atom, span, source);
+    let atoms = match keyword.as_str() {
+        "quote" | "import" => atoms,
+        "define" | "set!" => each(atoms, span, 2, expression),
+        // A module's name and exports come before its body.
+        "define-module" => each(atoms, span, 3, expression),
+        // Optional and keyword parameters may have default expressions.
+        "lambda" => each_at(atoms, span, |i, atom, span| match i {
+            0 => atom,
+            1 => within_list(atom, span, 0, &|param, span| within_list(param, span, 1, &expression)),
+            _ => locate(atom, span, source)
+        }),
+        "let" | "let*" | "letrec" | "letrec*" => {
+            // A named let has its name before the bindings.
+            let bindings = match atoms.get(1) {
+                Some(Atom::Identifier(_)) => 2,
+                _ => 1
+            };
+            each_at(atoms, span, |i, atom, span| match i {
+                _ if i == bindings => within_list(atom, span, 0, &|binding, span| {
+                    within_list(binding, span, 1, &expression)
+                }),
+                _ if i > bindings => locate(atom, span, source),
+                _ => atom
+            })
+        },
+        // (do ((var init step)...) (test result...) command...)
+        "do" => each_at(atoms, span, |i, atom, span| match i {
+            0 => atom,
+            1 => within_list(atom, span, 0, &|spec, span| within_list(spec, span, 1, &expression)),
+            2 => within_list(atom, span, 0, &expression),
+            _ => locate(atom, span, source)
+        }),
+        "cond" => each(atoms, span, 1, |clause, span| within_list(clause, span, 0, &expression)),
+        // (guard (var clause...) body...)
+        "guard" => each_at(atoms, span, |i, atom, span| match i {
+            0 => atom,
+            1 => within_list(atom, span, 1, &|clause, span| within_list(clause, span, 0, &expression)),
+            _ => locate(atom, span, source)
+        }),
+        _ => each(atoms, span, 0, expression)
+    };
+    Atom::Form(location, atoms)
+}
+
+// Applies `f` to the elements of a list from `from` on, with their spans.
+fn each<F>(atoms: Vec<Atom>, span: &Span, from: usize, f: F) -> Vec<Atom>
+    where F: Fn(Atom, &Span) -> Atom {
+    each_at(atoms, span, |i, atom, span| if i >= from { f(atom, span) } else { atom })
+}
+
+fn each_at<F>(atoms: Vec<Atom>, span: &Span, f: F) -> Vec<Atom>
+    where F: Fn(usize, Atom, &Span) -> Atom {
+    atoms.into_iter().enumerate().map(|(i, atom)| match span.elements.get(i) {
+        Some(span) => f(i, atom, span),
+        None => atom
+    }).collect()
+}
+
+// Applies `f` to the elements from `from` on of a list that isn't evaluated
+// itself, such as a binding, leaving anything else as it is.
+fn within_list(atom: Atom, span: &Span, from: usize, f: &dyn Fn(Atom, &Span) -> Atom) -> Atom {
+    match atom {
+        Atom::List(atoms) => Atom::List(each(atoms, span, from, f)),
+        atom => atom
+    }
+}
@@ -0,0 +1,161 @@
+use std::fmt;
+use std::fmt::{Display, Formatter, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+use tokenizer::{is_delimiter, tokenize, Token};
+
+// A lossless syntax tree of source code. Unlike the parser, which keeps only
+// the data, it keeps the whitespace and comments around them, so that it
+// displays as exactly the source it was read from. Tools such as the
+// formatter work on this instead of on atoms.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    // The nodes between a pair of parentheses.
+    List(Vec<Node>),
+    // The nodes after a quote, ending with the datum quoted.
+    Quote(Vec<Node>),
+    // An identifier, integer or string literal as written.
+    Token(String),
+    Whitespace(String),
+    // A comment from its semicolon up to the end of its line.
+    Comment(String)
+}
+
+impl Node {
+    // Whether the node is whitespace or a comment, rather than a datum.
+    pub fn is_trivia(&self) -> bool {
+        matches!(*self, Node::Whitespace(_) | Node::Comment(_))
+    }
+
+    // The identifier a token reads as, if it is one.
+    pub fn identifier(&self) -> Option<String> {
+        match *self {
+            Node::Token(ref text) => match tokenize(text) {
+                Ok(mut tokens) => match tokens.pop() {
+                    Some(Token::Identifier(name)) => Some(name),
+                    _ => None
+                },
+                Err(_) => None
+            },
+            _ => None
+        }
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Node::List(ref nodes) => {
+                fmt.write_char('(')?;
+                for node in nodes {
+                    node.fmt(fmt)?;
+                }
+                fmt.write_char(')')
+            },
+            Node::Quote(ref nodes) => {
+                fmt.write_char('\'')?;
+                for node in nodes {
+                    node.fmt(fmt)?;
+                }
+                Ok(())
+            },
+            Node::Token(ref text) | Node::Whitespace(ref text) | Node::Comment(ref text) => {
+                fmt.write_str(text)
+            }
+        }
+    }
+}
+
+// Reads the nodes of a whole source file, failing wherever the parser would,
+// including on lists and quotes nested more than `max_depth` deep.
+pub fn parse(source: &str, max_depth: usize) -> Result<Vec<Node>, &'static str> {
+    let mut chars = source.chars().peekable();
+    let nodes = parse_nodes(&mut chars, max_depth)?;
+    match chars.next() {
+        Some(_) => Err("unexpected )"),
+        None => Ok(nodes)
+    }
+}
+
+// Reads nodes up to a closing parenthesis or the end of the source, leaving
+// either one unconsumed. `depth` is how many more lists and quotes they may
+// nest.
+fn parse_nodes(chars: &mut Peekable<Chars>, depth: usize) -> Result<Vec<Node>, &'static str> {
+    let mut nodes = vec![];
+    while let Some(node) = parse_node(chars, depth)? {
+        nodes.push(node);
+    }
+    Ok(nodes)
+}
+
+fn parse_node(chars: &mut Peekable<Chars>, depth: usize) -> Result<Option<Node>, &'static str> {
+    let c = match chars.peek() {
+        Some(&c) if c != ')' => c,
+        _ => return Ok(None)
+    };
+    if (c == '(' || c == '\'') && depth == 0 {
+        return Err("maximum nesting depth exceeded")
+    }
+    let node = if c.is_whitespace() {
+        Node::Whitespace(take_while(chars, char::is_whitespace))
+    } else if c == ';' {
+        Node::Comment(take_while(chars, |c| c != '\n'))
+    } else if c == '(' {
+        chars.next();
+        let nodes = parse_nodes(chars, depth - 1)?;
+        if chars.next().is_none() {
+            return Err("unexpected end of input")
+        }
+        Node::List(nodes)
+    } else if c == '\'' {
+        chars.next();
+        let mut nodes = vec![];
+        loop {
+            match parse_node(chars, depth - 1)? {
+                Some(node) => {
+                    let trivia = node.is_trivia();
+                    nodes.push(node);
+                    if !trivia { break }
+                },
+                None if chars.peek().is_none() => return Err("quote at end of input"),
+                None => return Err("quote must be followed by a datum")
+            }
+        }
+        Node::Quote(nodes)
+    } else {
+        let text = if c == '"' || c == '|' {
+            take_delimited(chars, c)
+        } else {
+            take_while(chars, |c| !is_delimiter(c))
+        };
+        // The tokenizer has the final say on whether a token is valid.
+        tokenize(&text)?;
+        Node::Token(text)
+    };
+    Ok(Some(node))
+}
+
+fn take_while<F: Fn(char) -> bool>(chars: &mut Peekable<Chars>, predicate: F) -> String {
+    let mut text = String::new();
+    while let Some(&c) = chars.peek() {
+        if !predicate(c) { break }
+        text.push(c);
+        chars.next();
+    }
+    text
+}
+
+// Takes a string literal or barred identifier as written, escapes and all.
+fn take_delimited(chars: &mut Peekable<Chars>, delimiter: char) -> String {
+    let mut text = String::new();
+    text.extend(chars.next());
+    while let Some(c) = chars.next() {
+        text.push(c);
+        if c == '\\' {
+            text.extend(chars.next());
+        } else if c == delimiter {
+            break;
+        }
+    }
+    text
+}
@@ -1,23 +1,104 @@
+use std::iter::Peekable;
+use std::str::Chars;
+use diagnostic::{Diagnostic, Position};
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Token {
     OpenParen,
     CloseParen,
     Identifier(String),
     Quote,
-    Integer(i64)
+    Integer(i64),
+    String(String)
 }
 
 pub type TokenResult<T> = Result<T, &'static str>;
 
-// Input must be formatted so that each token is separated by a space.
+// Tokenizes a program, failing at the first invalid token. The tokens are
+// returned last first, so that the next one can be popped.
 pub fn tokenize(program: &str) -> TokenResult<Vec<Token>> {
+    let (tokens, diagnostics) = scan(program);
+    if let Some(diagnostic) = diagnostics.first() {
+        return Err(diagnostic.message)
+    }
+    Ok(tokens.into_iter().rev().map(|(token, _)| token).collect())
+}
+
+// Tokenizes a whole program in order, noting where each token starts. An
+// invalid token is left out with a diagnostic, and scanning carries on after
+// it.
+pub fn scan(program: &str) -> (Vec<(Token, Position)>, Vec<Diagnostic>) {
     let mut tokens = vec![];
-    for token in program.split_whitespace() {
-        let token = try!(match_token(token));
-        tokens.push(token);
+    let mut diagnostics = vec![];
+    let mut chars = Scanner { chars: program.chars().peekable(), position: Position::start() };
+    while let Some(c) = chars.peek() {
+        let position = chars.position;
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        // Comments run from a semicolon to the end of the line.
+        if c == ';' {
+            while chars.next().is_some_and(|c| c != '\n') {}
+            continue;
+        }
+        let token = match c {
+            '(' | ')' | '\'' => {
+                chars.next();
+                match_token(&c.to_string())
+            },
+            '"' => {
+                chars.next();
+                match_delimited(&mut chars, '"').map(Token::String)
+            },
+            // |...| reads everything up to the closing bar as one identifier.
+            '|' => {
+                chars.next();
+                match_delimited(&mut chars, '|').map(Token::Identifier)
+            },
+            _ => {
+                let mut token = String::new();
+                while let Some(c) = chars.peek() {
+                    if is_delimiter(c) { break }
+                    token.push(c);
+                    chars.next();
+                }
+                match_token(&token)
+            }
+        };
+        match token {
+            Ok(token) => tokens.push((token, position)),
+            Err(message) => diagnostics.push(Diagnostic { position, message })
+        }
+    }
+    (tokens, diagnostics)
+}
+
+// Characters of a program, keeping track of where the next one is.
+struct Scanner<'a> {
+    chars: Peekable<Chars<'a>>,
+    position: Position
+}
+
+impl<'a> Scanner<'a> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
     }
-    tokens.reverse();
-    Ok(tokens)
+}
+
+pub fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || c == '(' || c == ')' || c == '"' || c == '|' || c == ';'
 }
 
 fn match_token(token: &str) -> TokenResult<Token> {
@@ -32,9 +113,37 @@ fn match_token(token: &str) -> TokenResult<Token> {
 
 fn match_long_token(token: &str) -> TokenResult<Token> {
     assert!(!token.is_empty());
-    if token.chars().all(|c| c.is_digit(10)) {
-        Ok(Token::Integer(token.parse().unwrap()))
+    let digits = token.strip_prefix('-').unwrap_or(token);
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        token.parse().map(Token::Integer).map_err(|_| "integer literal out of range")
+    } else if token.starts_with("#<") {
+        Err("unreadable object")
     } else {
         Ok(Token::Identifier(token.to_string()))
     }
 }
+
+// Reads the rest of a string literal, or an identifier between bars, whose
+// opening delimiter has been consumed.
+fn match_delimited(chars: &mut Scanner, delimiter: char) -> TokenResult<String> {
+    let mut string = String::new();
+    // An unknown escape is reported once the literal is over, so that its
+    // remainder isn't scanned as more tokens.
+    let mut error = None;
+    loop {
+        match chars.next() {
+            Some(c) if c == delimiter => return error.map_or(Ok(string), Err),
+            Some('\\') => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some('r') => string.push('\r'),
+                Some('\\') => string.push('\\'),
+                Some(c) if c == delimiter => string.push(c),
+                Some(_) => error = error.or(Some("unknown escape in string literal")),
+                None => return Err("unterminated string literal")
+            },
+            Some(c) => string.push(c),
+            None => return Err("unterminated string literal")
+        }
+    }
+}
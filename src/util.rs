@@ -2,4 +2,13 @@ pub fn prepend<T>(item: T, items: &mut Vec<T>) -> Vec<T> {
     let mut new = vec![item];
     new.append(items);
     new
-}
\ No newline at end of file
+}
+// Cuts text down to `width` characters, ending with ... if anything was cut.
+pub fn shorten(text: String, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text
+    }
+    let mut text: String = text.chars().take(width - 3).collect();
+    text.push_str("...");
+    text
+}
@@ -0,0 +1,57 @@
+extern crate lithp;
+
+mod common;
+
+use std::collections::HashSet;
+use common::{eval, lithp};
+use lithp::atom::Atom;
+use lithp::capability::Capability;
+use lithp::eval::{Interpreter, Options};
+
+fn allowing(capabilities: &[Capability]) -> Interpreter {
+    Interpreter::with_options(Options { capabilities: capabilities.iter().cloned().collect(), ..Options::default() })
+}
+
+#[test]
+fn every_listed_built_in_can_be_called() {
+    let mut interpreter = Interpreter::new();
+    for name in interpreter.allowed_built_ins() {
+        let source = format!("(procedure? {})", name);
+        assert_eq!(eval(&mut interpreter, &source).unwrap().to_string(), "#t", "{} isn't a procedure", name);
+    }
+}
+
+#[test]
+fn special_forms_arent_listed_as_built_ins() {
+    let (output, _) = lithp(&["--list-built-ins"], "");
+    let listed: HashSet<&str> = output.lines().flat_map(|line| line.split_whitespace().skip(1)).collect();
+    for name in ["define", "let", "lambda", "!="] {
+        assert!(!listed.contains(name), "{} is listed", name);
+    }
+    assert!(listed.contains("car") && listed.contains("current-second"));
+}
+
+#[test]
+fn time_gates_the_clocks() {
+    let mut interpreter = allowing(&[Capability::Pure]);
+    assert_eq!(eval(&mut interpreter, "(current-jiffy)").unwrap_err().to_string(),
+               "unknown identifier current-jiffy");
+    assert!(interpreter.denied_built_ins().contains(&"current-second"));
+    let mut interpreter = allowing(&[Capability::Pure, Capability::Time]);
+    let elapsed = "(let ((start (current-jiffy))) (- (current-jiffy) start))";
+    assert_eq!(eval(&mut interpreter, "(jiffies-per-second)").unwrap().to_string(), "1000000");
+    assert!(matches!(eval(&mut interpreter, elapsed).unwrap().to_string().parse::<i64>(), Ok(n) if n >= 0));
+    assert_eq!(eval(&mut interpreter, "(> (current-second) 0)").unwrap().to_string(), "#t");
+}
+
+#[test]
+fn built_ins_handed_in_are_checked_when_called() {
+    let mut interpreter = allowing(&[Capability::Pure]);
+    let call = Atom::List(vec![Atom::Builtin("gc")]);
+    assert_eq!(interpreter.eval_form(call).unwrap_err().to_string(), "calling gc requires the process capability");
+    let define = Atom::List(vec![Atom::Identifier("define".to_string()), Atom::Identifier("collect".to_string()),
+                                 Atom::Builtin("gc")]);
+    interpreter.eval_form(define).unwrap();
+    assert_eq!(eval(&mut interpreter, "(guard (e (#t (error-object-message e))) (collect))").unwrap().to_string(),
+               "\"calling gc requires the process capability\"");
+}
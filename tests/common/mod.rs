@@ -0,0 +1,73 @@
+// Helpers shared by the tests, not all of which each test file uses.
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use lithp::atom::Atom;
+use lithp::error::Error;
+use lithp::eval::Interpreter;
+use lithp::parser::{parse_program, DEFAULT_MAX_DEPTH};
+
+// Evaluates each form of `source` in turn, returning the value of the last.
+pub fn eval(interpreter: &mut Interpreter, source: &str) -> Result<Atom, Error> {
+    let parsed = parse_program(source, DEFAULT_MAX_DEPTH);
+    assert!(parsed.diagnostics.is_empty(), "syntax errors in {:?}: {:?}", source, parsed.diagnostics);
+    let forms = match parsed.program {
+        Atom::List(forms) => forms,
+        _ => unreachable!()
+    };
+    let mut value = Atom::Identifier(String::new());
+    for form in forms {
+        value = interpreter.eval_form(form)?;
+    }
+    Ok(value)
+}
+
+// The value of `source` in a fresh interpreter, written as `write` would.
+pub fn value(source: &str) -> String {
+    match eval(&mut Interpreter::new(), source) {
+        Ok(value) => value.to_string(),
+        Err(error) => panic!("{:?} failed: {}", source, error)
+    }
+}
+
+// The message of the error `source` fails with in a fresh interpreter.
+pub fn error(source: &str) -> String {
+    match eval(&mut Interpreter::new(), source) {
+        Ok(value) => panic!("{:?} returned {} rather than failing", source, value),
+        Err(error) => error.to_string()
+    }
+}
+
+// Runs the lithp command with `args` and `input` on standard input,
+// returning what it printed and whether it exited successfully.
+pub fn lithp(args: &[&str], input: &str) -> (String, bool) {
+    lithp_in(Path::new("."), args, input)
+}
+
+// Like `lithp`, run in `directory`.
+pub fn lithp_in(directory: &Path, args: &[&str], input: &str) -> (String, bool) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lithp"))
+        .args(args)
+        .current_dir(directory)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("lithp failed to start");
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (String::from_utf8_lossy(&output.stdout).into_owned(), output.status.success())
+}
+
+// An empty directory for the files of the test `name`, which the test
+// removes once it is done with it.
+pub fn scratch(name: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("lithp-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
@@ -0,0 +1,69 @@
+extern crate lithp;
+
+mod common;
+
+use common::{error, lithp, value};
+
+#[test]
+fn guard_catches_raised_objects() {
+    assert_eq!(value("(guard (e (#t (list 'caught e))) (raise 'oops))"), "(caught oops)");
+    assert_eq!(value("(guard (e ((error-object? e) (error-object-message e))) (error \"bad\" 1 2))"), "\"bad\"");
+    assert_eq!(value("(guard (e ((error-object? e) (error-object-irritants e))) (error \"bad\" 1 2))"), "(1 2)");
+}
+
+#[test]
+fn guard_reraises_what_no_clause_handles() {
+    let program = "(guard (outer (#t (list 'outer outer)))
+                     (guard (inner ((eq? inner 'other) 'inner))
+                       (raise 'oops)))";
+    assert_eq!(value(program), "(outer oops)");
+    assert_eq!(error("(guard (e ((eq? e 'other) 1)) (raise 'oops))"), "uncaught raise of oops");
+}
+
+#[test]
+fn interpreter_errors_are_catchable_conditions() {
+    assert_eq!(value("(guard (e (else (error-object-message e))) (car (list)))"), "\"invalid argument to car\"");
+    assert_eq!(value("(guard (e (else (error-object-message e))) undefined-name)"), "\"unknown identifier\"");
+}
+
+#[test]
+fn division_errors_are_catchable() {
+    assert_eq!(value("(guard (e (else (error-object-message e))) (/ 1 0))"), "\"division by zero\"");
+    assert_eq!(value("(guard (e (else (error-object-message e))) (/ -9223372036854775808 -1))"),
+               "\"integer overflow in division\"");
+    assert_eq!(value("(/ 100 5 2)"), "10");
+}
+
+#[test]
+fn arithmetic_overflow_is_catchable() {
+    assert_eq!(value("(guard (e (else (error-object-message e))) (+ 9223372036854775807 1))"), "\"integer overflow\"");
+    assert_eq!(value("(guard (e (else (error-object-message e))) (- -9223372036854775808 1))"), "\"integer overflow\"");
+    assert_eq!(value("(guard (e (else (error-object-message e))) (* 4611686018427387904 2))"), "\"integer overflow\"");
+    assert_eq!(error("(+ 1 9223372036854775806 1)"), "integer overflow");
+    assert_eq!(value("(list (+ 9223372036854775806 1) (- -9223372036854775807 1) (* -1 9223372036854775807))"),
+               "(9223372036854775807 -9223372036854775808 -9223372036854775807)");
+}
+
+#[test]
+fn handlers_of_continuable_raises_return_to_the_raise() {
+    assert_eq!(value("(with-exception-handler (lambda (e) (* e 2)) (lambda () (+ 1 (raise-continuable 20))))"), "41");
+    assert_eq!(error("(with-exception-handler (lambda (e) 0) (lambda () (raise 'oops)))"),
+               "handler returned from non-continuable raise oops");
+}
+
+#[test]
+fn errors_raised_in_handlers_go_to_the_next_handler_out() {
+    let program = "(guard (e (#t (list 'guard e)))
+                     (with-exception-handler (lambda (e) (raise (list 'handler e)))
+                                             (lambda () (raise 'oops))))";
+    assert_eq!(value(program), "(guard (handler oops))");
+}
+
+#[test]
+fn tracebacks_show_the_arguments_calls_were_given() {
+    let program = "(define f (lambda (xs g) (set! xs 0) (set! g 0) (gc) (car xs)))
+                   (f (list 1 2) (let ((y 1)) (lambda () y)))";
+    let (output, _) = lithp(&[], program);
+    assert_eq!(output, "Traceback (most recent call last):\n  <stdin>:2:20: (f (1 2) #<lambda>)\n\
+                        Evaluation Error: invalid argument to car\n");
+}
@@ -0,0 +1,79 @@
+extern crate lithp;
+
+mod common;
+
+use common::value;
+
+#[test]
+fn escaping_continuations_abandon_the_rest_of_the_computation() {
+    assert_eq!(value("(+ 1 (call/cc (lambda (k) (+ 10 (k 1)))))"), "2");
+    assert_eq!(value("(call-with-current-continuation (lambda (k) 5))"), "5");
+}
+
+#[test]
+fn continuations_can_be_reentered() {
+    let program = "
+        (let ((k #f) (n 0))
+          (let ((x (call/cc (lambda (c) (set! k c) 0))))
+            (set! n (+ n 1))
+            (cond ((< x 3) (k (+ x 1)))
+                  (#t (list x n)))))";
+    assert_eq!(value(program), "(3 4)");
+}
+
+#[test]
+fn dynamic_wind_runs_before_body_and_after_in_order() {
+    let program = r#"
+        (with-output-to-string
+          (lambda ()
+            (dynamic-wind (lambda () (display "before "))
+                          (lambda () (display "during "))
+                          (lambda () (display "after")))))"#;
+    assert_eq!(value(program), r#""before during after""#);
+}
+
+#[test]
+fn escaping_a_dynamic_wind_runs_its_after_thunk() {
+    let program = r#"
+        (with-output-to-string
+          (lambda ()
+            (call/cc
+              (lambda (k)
+                (dynamic-wind (lambda () (display "in "))
+                              (lambda () (k 'escaped) (display "never "))
+                              (lambda () (display "out ")))))
+            (display "done")))"#;
+    assert_eq!(value(program), r#""in out done""#);
+}
+
+#[test]
+fn reentering_a_dynamic_wind_runs_its_before_thunk_again() {
+    let program = r#"
+        (define saved #f)
+        (define count 0)
+        (with-output-to-string
+          (lambda ()
+            (dynamic-wind (lambda () (display "[in]"))
+                          (lambda () (call/cc (lambda (k) (set! saved k))) (display "body"))
+                          (lambda () (display "[out]")))
+            (set! count (+ count 1))
+            (cond ((< count 3) (saved 'again))
+                  (#t 'done))))"#;
+    assert_eq!(value(program), r#""[in]body[out][in]body[out][in]body[out]""#);
+}
+
+#[test]
+fn nested_winds_unwind_innermost_first() {
+    let program = r#"
+        (with-output-to-string
+          (lambda ()
+            (call/cc
+              (lambda (k)
+                (dynamic-wind (lambda () (display "a+ "))
+                              (lambda ()
+                                (dynamic-wind (lambda () (display "b+ "))
+                                              (lambda () (k 0))
+                                              (lambda () (display "b- "))))
+                              (lambda () (display "a- ")))))))"#;
+    assert_eq!(value(program), r#""a+ b+ b- a- ""#);
+}
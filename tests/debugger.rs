@@ -0,0 +1,80 @@
+extern crate lithp;
+
+mod common;
+
+use std::fs;
+use common::{lithp, scratch};
+
+const PROGRAM: &str = "(define double (lambda (n) (* n 2)))
+(define x 1)
+(let ((y (double x)))
+  (list x y))
+(display (double 5))
+";
+
+// What the debugger prints when `program` is run under it, taking each of
+// `commands` in turn.
+fn debug(name: &str, program: &str, commands: &[&str]) -> String {
+    let directory = scratch(name);
+    let path = directory.join("program.lithp");
+    fs::write(&path, program).unwrap();
+    let path = path.to_str().unwrap();
+    let (output, _) = lithp(&["--debug", path], &format!("{}\n", commands.join("\n")));
+    fs::remove_dir_all(&directory).unwrap();
+    output.replace(path, "program.lithp")
+}
+
+#[test]
+fn step_goes_into_calls_and_next_over_them() {
+    assert_eq!(debug("step", PROGRAM, &["n", "n", "s", "s", "s", "n", "q"]),
+               "program.lithp:1:1: (define double (lambda (n) (* n 2)))\n\
+                (debug) program.lithp:2:1: (define x 1)\n\
+                (debug) program.lithp:3:1: (let ((y (double x))) (list x y))\n\
+                (debug) program.lithp:3:10: (double x)\n\
+                (debug) program.lithp:1:28: (* n 2)\n\
+                (debug) program.lithp:4:3: (list x y)\n\
+                (debug) program.lithp:5:1: (display (double 5))\n\
+                (debug) ");
+}
+
+#[test]
+fn breakpoints_pause_at_lines_and_calls_until_deleted() {
+    assert_eq!(debug("breakpoints", PROGRAM, &["b double", "b 4", "b", "c", "c", "d double", "c"]),
+               "program.lithp:1:1: (define double (lambda (n) (* n 2)))\n\
+                (debug) breakpoint at double\n\
+                (debug) breakpoint at line 4\n\
+                (debug) double\nline 4\n\
+                (debug) program.lithp:1:28: (* n 2)\n\
+                (debug) program.lithp:4:3: (list x y)\n\
+                (debug) (debug) 10");
+}
+
+#[test]
+fn print_and_locals_look_at_where_evaluation_is_paused() {
+    assert_eq!(debug("print", PROGRAM, &["b double", "c", "bt", "l", "p (+ n 1)", "p (car n)", "f", "l", "d", "c"]),
+               "program.lithp:1:1: (define double (lambda (n) (* n 2)))\n\
+                (debug) breakpoint at double\n\
+                (debug) program.lithp:1:28: (* n 2)\n\
+                (debug) #0 (double 1) called at program.lithp:3:10\n\
+                (debug) n = 1\n\
+                (debug) 2\n\
+                (debug) Evaluation Error: invalid argument to car\n\
+                (debug) program.lithp:4:3: (list x y)\n\
+                (debug) y = 2\n\
+                (debug) (debug) 10");
+}
+
+#[test]
+fn collecting_garbage_while_paused_keeps_what_is_paused_on() {
+    let program = "(let ((x 1))\n  (list x))\n(display 'done)\n";
+    let count = "p (do ((i 0 (+ i 1))) ((= i 20000) i))";
+    assert_eq!(debug("collect", program, &["s", "p (gc)", "l", count, "l", "s", "c"]),
+               "program.lithp:1:1: (let ((x 1)) (list x))\n\
+                (debug) program.lithp:2:3: (list x)\n\
+                (debug) 0\n\
+                (debug) x = 1\n\
+                (debug) 20000\n\
+                (debug) x = 1\n\
+                (debug) program.lithp:3:1: (display 'done)\n\
+                (debug) done");
+}
@@ -0,0 +1,34 @@
+extern crate lithp;
+
+mod common;
+
+use common::value;
+
+#[test]
+fn eq_compares_identity() {
+    assert_eq!(value("(list (eq? 'a 'a) (eq? car car) (eq? (list) (list)) (eq? \"a\" \"a\"))"), "(#t #t #t #f)");
+    assert_eq!(value("(let ((s \"a\") (f (lambda () 1))) (list (eq? s s) (eq? f f)))"), "(#t #t)");
+}
+
+#[test]
+fn eqv_is_eq() {
+    assert_eq!(value("(list (eqv? 100000000000 100000000000) (eqv? \"a\" \"a\"))"), "(#t #f)");
+    assert_eq!(value("(eqv? (lambda () 1) (lambda () 1))"), "#f");
+}
+
+#[test]
+fn lists_are_the_same_if_their_elements_are() {
+    assert_eq!(value("(list (eq? (list 1 'a) (list 1 'a)) (eq? (list 1) (list 2)))"), "(#t #f)");
+    assert_eq!(value("(let ((s \"a\")) (list (eq? (list s) (list s)) (eq? (list s) (list \"a\"))))"), "(#t #f)");
+}
+
+#[test]
+fn equal_compares_structure() {
+    assert_eq!(value("(equal? (list 1 (list \"a\" 'b)) (list 1 (list \"a\" 'b)))"), "#t");
+    assert_eq!(value("(list (equal? 1 \"1\") (equal? (list 1) (list 1 2)))"), "(#f #f)");
+}
+
+#[test]
+fn comparisons_take_any_number_of_arguments() {
+    assert_eq!(value("(list (= 1 1 1) (< 1 2 3) (< 1 2 2) (> 3 2 1))"), "(#t #t #f #t)");
+}
@@ -0,0 +1,68 @@
+extern crate lithp;
+
+mod common;
+
+use std::fs;
+use common::{lithp, scratch};
+use lithp::format::format;
+use lithp::parser::DEFAULT_MAX_DEPTH;
+use lithp::pretty::DEFAULT_WIDTH;
+
+const SOURCE: &str = "; The factorial.
+(define fact   (lambda (n) ; n at least 0
+  (cond ((= n 0) 1)
+
+
+        (else (* n (fact (- n 1)))))))
+(define xs '(1 2 3))   ; trailing
+;; last comment
+";
+
+const FORMATTED: &str = "; The factorial.
+(define fact
+  (lambda (n) ; n at least 0
+    (cond ((= n 0) 1)
+
+          (else (* n (fact (- n 1)))))))
+(define xs '(1 2 3)) ; trailing
+;; last comment
+";
+
+fn formatted(source: &str) -> String {
+    format(source, DEFAULT_WIDTH, DEFAULT_MAX_DEPTH).unwrap()
+}
+
+#[test]
+fn formatting_keeps_comments_and_single_empty_lines() {
+    assert_eq!(formatted(SOURCE), FORMATTED);
+    assert_eq!(formatted("(a ; one\n b ; two\n )\n"), "(a ; one\n b ; two\n)\n");
+    assert_eq!(formatted(""), "");
+}
+
+#[test]
+fn formatting_formatted_source_changes_nothing() {
+    let samples = [SOURCE, "(let ((a 1)) (display a) (newline))", "(1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 \
+                    20 21 22 23 24 25 26 27 28 29 30 31 32 33 34 35 36 37 38 39 40)",
+                   ";; only a comment\n", "(a\n\n\n b)\n\n\n(c)"];
+    for sample in samples {
+        let once = formatted(sample);
+        assert_eq!(formatted(&once), once, "formatting {:?} again changed it", sample);
+    }
+}
+
+#[test]
+fn check_fails_on_unformatted_input_without_changing_it() {
+    assert_eq!(lithp(&["fmt", "--check"], FORMATTED), (String::new(), true));
+    assert_eq!(lithp(&["fmt", "--check"], SOURCE), (String::new(), false));
+    assert_eq!(lithp(&["fmt"], SOURCE), (FORMATTED.to_string(), true));
+    let directory = scratch("fmt");
+    let path = directory.join("fact.lithp");
+    fs::write(&path, SOURCE).unwrap();
+    let path = path.to_str().unwrap();
+    assert_eq!(lithp(&["fmt", "--check", path], ""), (format!("{}: not formatted\n", path), false));
+    assert_eq!(fs::read_to_string(path).unwrap(), SOURCE);
+    assert_eq!(lithp(&["fmt", path], ""), (String::new(), true));
+    assert_eq!(fs::read_to_string(path).unwrap(), FORMATTED);
+    assert_eq!(lithp(&["fmt", "--check", path], ""), (String::new(), true));
+    fs::remove_dir_all(directory).unwrap();
+}
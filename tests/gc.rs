@@ -0,0 +1,55 @@
+extern crate lithp;
+
+mod common;
+
+use common::{eval, value};
+use lithp::atom::Atom;
+use lithp::eval::Interpreter;
+
+// Makes more garbage than the collector lets build up between collections.
+const CHURN: &str = "(do ((i 0 (+ i 1))) ((= i 3000)) (let ((x i)) x))";
+
+#[test]
+fn recursive_closures_survive_collections() {
+    let program = format!("
+        (define count-down (lambda (n) (cond ((= n 0) 'done) (#t (count-down (- n 1))))))
+        (define make-adder (lambda (n) (lambda (x) (+ x n))))
+        (define add5 (make-adder 5))
+        {}
+        (gc)
+        (list (count-down 100) (add5 10) (> (gc) -1))", CHURN);
+    assert_eq!(value(&program), "(done 15 #t)");
+}
+
+#[test]
+fn unreachable_scopes_are_freed() {
+    let mut interpreter = Interpreter::new();
+    eval(&mut interpreter, CHURN).unwrap();
+    let stats = eval(&mut interpreter, "(gc) (gc-stats)").unwrap().to_string();
+    let freed: i64 = stats.split("(freed ").nth(1).unwrap().split(')').next().unwrap().parse().unwrap();
+    assert!(freed >= 3000, "{}", stats);
+}
+
+#[test]
+fn rooted_values_are_kept_alive() {
+    let mut interpreter = Interpreter::new();
+    let adder = eval(&mut interpreter, "((lambda (n) (lambda (x) (+ x n))) 5)").unwrap();
+    let root = interpreter.root(adder);
+    eval(&mut interpreter, &format!("{} (gc)", CHURN)).unwrap();
+    let call = Atom::List(vec![interpreter.rooted(&root).clone(), Atom::Integer(1)]);
+    assert_eq!(interpreter.eval_form(call).unwrap(), Atom::Integer(6));
+
+    let adder = interpreter.release(root);
+    eval(&mut interpreter, &format!("{} (gc)", CHURN)).unwrap();
+    let error = interpreter.eval_form(Atom::List(vec![adder, Atom::Integer(1)])).unwrap_err();
+    assert!(error.to_string().starts_with("call of a procedure that was collected"), "{}", error);
+}
+
+#[test]
+fn collected_procedures_never_alias_new_scopes() {
+    let mut interpreter = Interpreter::new();
+    let stale = eval(&mut interpreter, "((lambda (n) (lambda () n)) 'old)").unwrap();
+    // The slots freed are reused by the scopes made after the collection.
+    eval(&mut interpreter, "(gc) (define keep (map (lambda (n) (lambda () n)) (iota 100)))").unwrap();
+    assert!(interpreter.eval_form(Atom::List(vec![stale])).is_err());
+}
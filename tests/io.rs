@@ -0,0 +1,105 @@
+extern crate lithp;
+
+mod common;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use common::eval;
+use lithp::eval::{Interpreter, Options};
+use lithp::port::Io;
+
+type Buffer = Rc<RefCell<Vec<u8>>>;
+
+// A console and file system held in memory.
+#[derive(Clone, Default)]
+struct Memory {
+    console: Buffer,
+    input: String,
+    files: Rc<RefCell<HashMap<PathBuf, Buffer>>>
+}
+
+struct Writer(Buffer);
+
+impl Write for Writer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Io for Memory {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.console.borrow_mut().write_all(text.as_bytes())
+    }
+
+    fn stdin(&mut self) -> io::Result<Box<dyn BufRead>> {
+        Ok(Box::new(Cursor::new(self.input.clone().into_bytes())))
+    }
+
+    fn open_input(&mut self, path: &Path) -> io::Result<Box<dyn BufRead>> {
+        match self.files.borrow().get(path) {
+            Some(file) => Ok(Box::new(Cursor::new(file.borrow().clone()))),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "no such file"))
+        }
+    }
+
+    fn open_output(&mut self, path: &Path) -> io::Result<Box<dyn Write>> {
+        let file = Buffer::default();
+        self.files.borrow_mut().insert(path.to_path_buf(), file.clone());
+        Ok(Box::new(Writer(file)))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.borrow().contains_key(path)
+    }
+}
+
+impl Memory {
+    fn console(&self) -> String {
+        String::from_utf8(self.console.borrow().clone()).unwrap()
+    }
+}
+
+fn run(memory: &Memory, source: &str) -> String {
+    let mut interpreter = Interpreter::with_io(Options::default(), Box::new(memory.clone()));
+    match eval(&mut interpreter, source) {
+        Ok(value) => value.to_string(),
+        Err(error) => format!("error: {}", error)
+    }
+}
+
+#[test]
+fn output_goes_through_the_io() {
+    let memory = Memory::default();
+    run(&memory, "(display \"a b\") (newline) (write \"a b\")");
+    assert_eq!(memory.console(), "a b\n\"a b\"");
+}
+
+#[test]
+fn input_comes_from_the_io() {
+    let memory = Memory { input: "(1 2) rest of line\nnext\n".to_string(), ..Memory::default() };
+    assert_eq!(run(&memory, "(list (read) (read-line) (read-line) (eof-object? (read)))"),
+               "((1 2) \"rest of line\" \"next\" #t)");
+}
+
+#[test]
+fn files_are_written_and_read_through_ports() {
+    let memory = Memory::default();
+    run(&memory, "(define out (open-output-file \"f.txt\")) (write '(a \"b\") out) (close-port out)");
+    assert_eq!(run(&memory, "(file-exists? \"f.txt\")"), "#t");
+    assert_eq!(run(&memory, "(call-with-input-file \"f.txt\" read)"), "(a \"b\")");
+    assert!(run(&memory, "(open-input-file \"missing.txt\")").starts_with("error: "));
+}
+
+#[test]
+fn output_to_a_string() {
+    let memory = Memory::default();
+    assert_eq!(run(&memory, "(with-output-to-string (lambda () (display 1) (write \"2\")))"), "\"1\\\"2\\\"\"");
+    assert_eq!(memory.console(), "");
+}
@@ -0,0 +1,148 @@
+extern crate lithp;
+
+mod common;
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use common::eval;
+use lithp::eval::{Interpreter, Limits, Options};
+use lithp::port::Io;
+
+// A console that takes a while to write to, so that time passes within a
+// single built in.
+struct Slow;
+
+impl Io for Slow {
+    fn write(&mut self, _: &str) -> io::Result<()> {
+        thread::sleep(Duration::from_millis(50));
+        Ok(())
+    }
+
+    fn stdin(&mut self) -> io::Result<Box<dyn BufRead>> {
+        Ok(Box::new(io::empty()))
+    }
+
+    fn open_input(&mut self, _: &Path) -> io::Result<Box<dyn BufRead>> {
+        Err(io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn open_output(&mut self, _: &Path) -> io::Result<Box<dyn Write>> {
+        Err(io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn exists(&self, _: &Path) -> bool {
+        false
+    }
+}
+
+fn limited(limits: Limits) -> Interpreter {
+    Interpreter::with_io(Options { limits, ..Options::default() }, Box::new(Slow))
+}
+
+// The error `source` fails with, which must be from running out of a
+// resource.
+fn exhausted(interpreter: &mut Interpreter, source: &str) -> String {
+    match eval(interpreter, source) {
+        Ok(value) => panic!("{:?} returned {}", source, value),
+        Err(error) => {
+            assert!(error.resource_exhausted, "{:?} failed with {}", source, error);
+            error.to_string()
+        }
+    }
+}
+
+#[test]
+fn running_out_of_steps_cant_be_caught() {
+    let mut interpreter = limited(Limits { steps: Some(1000), ..Limits::default() });
+    assert_eq!(exhausted(&mut interpreter, "(guard (e (#t e)) (let loop () (loop)))"), "resource exhausted steps");
+}
+
+#[test]
+fn running_out_of_time_in_a_handler_cant_be_caught() {
+    let mut interpreter = limited(Limits { time: Some(Duration::from_millis(20)), ..Limits::default() });
+    let program = "(guard (e (#t e))
+                     (with-exception-handler (lambda (e) 1) (lambda () (display 1) undefined-var)))";
+    assert_eq!(exhausted(&mut interpreter, program), "resource exhausted time");
+}
+
+#[test]
+fn reset_limits_starts_the_budgets_afresh() {
+    let limits = Limits { steps: Some(300), time: Some(Duration::from_millis(40)), ..Limits::default() };
+    let mut interpreter = limited(limits);
+    thread::sleep(Duration::from_millis(60));
+    interpreter.reset_limits();
+    assert_eq!(eval(&mut interpreter, "(length (range 0 5))").unwrap().to_string(), "5");
+    let count = "(do ((i 0 (+ i 1))) ((= i 10) i))";
+    assert!(eval(&mut interpreter, count).is_ok());
+    assert_eq!(exhausted(&mut interpreter, count), "resource exhausted steps");
+    interpreter.reset_limits();
+    assert!(eval(&mut interpreter, count).is_ok());
+}
+
+#[test]
+fn the_repl_doesnt_count_time_spent_waiting() {
+    use std::process::{Command, Stdio};
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lithp"))
+        .args(["--repl", "--timeout=200"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    thread::sleep(Duration::from_millis(400));
+    stdin.write_all(b"(length (list 1 2))\n").unwrap();
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "> 2\n> \n");
+}
+
+#[test]
+fn building_lists_counts_against_the_cells() {
+    let mut interpreter = limited(Limits { cells: Some(1000), ..Limits::default() });
+    assert_eq!(exhausted(&mut interpreter, "(length (iota 1000000000))"), "resource exhausted cells");
+    let doubling = "(let loop ((xs (list 1))) (loop (append xs xs)))";
+    assert_eq!(exhausted(&mut interpreter, doubling), "resource exhausted cells");
+    assert_eq!(eval(&mut interpreter, "(length (iota 100))").unwrap().to_string(), "100");
+}
+
+#[test]
+fn writing_to_a_string_counts_against_the_cells() {
+    let mut interpreter = limited(Limits { cells: Some(1000), ..Limits::default() });
+    let program = "(with-output-to-string (lambda () (let loop () (display \"abcdefgh\") (loop))))";
+    assert_eq!(exhausted(&mut interpreter, program), "resource exhausted cells");
+}
+
+#[test]
+fn values_cant_nest_deeper_than_the_limit() {
+    let mut interpreter = limited(Limits::default());
+    let nested = "(do ((i 0 (+ i 1)) (xs '() (list xs))) ((= i 30000) xs))";
+    assert_eq!(eval(&mut interpreter, nested).unwrap_err().to_string(), "maximum nesting depth exceeded");
+    let rest = "(define f (lambda xs xs)) (do ((i 0 (+ i 1)) (xs '() (f xs))) ((= i 30000) xs))";
+    assert_eq!(eval(&mut interpreter, rest).unwrap_err().to_string(), "maximum nesting depth exceeded");
+    let caught = "(guard (e (#t (error-object-message e))) (do ((xs '() (list xs))) (#f)))";
+    assert_eq!(eval(&mut interpreter, caught).unwrap().to_string(), "\"maximum nesting depth exceeded\"");
+}
+
+#[test]
+fn long_chains_of_continuations_can_be_dropped() {
+    let mut interpreter = limited(Limits::default());
+    let chain = "(do ((i 0 (+ i 1)) (k #f (car (cdr (list k (call/cc (lambda (c) c))))))) ((= i 5000) 1))";
+    assert_eq!(eval(&mut interpreter, chain).unwrap().to_string(), "1");
+}
+
+#[test]
+fn overflow_is_returned_as_an_error() {
+    let limits = Limits { steps: Some(1000), cells: Some(1000), time: Some(Duration::MAX) };
+    let mut interpreter = limited(limits);
+    for source in ["(+ 9223372036854775807 1)", "(- -9223372036854775808 1)", "(* 9223372036854775807 2)",
+                   "(/ -9223372036854775808 -1)", "(iota 2 9223372036854775807)"] {
+        match eval(&mut interpreter, source) {
+            Ok(value) => panic!("{:?} returned {}", source, value),
+            Err(error) => assert!(!error.resource_exhausted && error.to_string().starts_with("integer overflow"),
+                                  "{:?} failed with {}", source, error)
+        }
+    }
+    assert_eq!(eval(&mut interpreter, "(+ 1 2)").unwrap().to_string(), "3");
+}
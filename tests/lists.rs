@@ -0,0 +1,52 @@
+extern crate lithp;
+
+mod common;
+
+use common::{error, value};
+
+#[test]
+fn higher_order_procedures() {
+    assert_eq!(value("(map + (list 1 2) (list 10 20))"), "(11 22)");
+    assert_eq!(value("(filter (lambda (x) (< x 2)) (list 1 2 0 3))"), "(1 0)");
+    assert_eq!(value("(fold-left - 0 (list 1 2 3))"), "-6");
+    assert_eq!(value("(fold-right list (list) (list 1 2))"), "(1 (2 ()))");
+    assert_eq!(value("(reduce + 0 (list))"), "0");
+    assert_eq!(value("(with-output-to-string (lambda () (for-each display (list 1 2))))"), "\"12\"");
+}
+
+#[test]
+fn sort_is_stable() {
+    let pairs = "(list (list 2 'a) (list 1 'b) (list 2 'c) (list 1 'd))";
+    let by_first = "(lambda (a b) (< (car a) (car b)))";
+    assert_eq!(value(&format!("(sort {} {})", pairs, by_first)), "((1 b) (1 d) (2 a) (2 c))");
+}
+
+#[test]
+fn procedures_passed_in_can_escape() {
+    let program = "(call/cc (lambda (k) (map (lambda (x) (cond ((= x 2) (k 'escaped)) (#t x))) (list 1 2 3))))";
+    assert_eq!(value(program), "escaped");
+    assert_eq!(value("(guard (e (#t e)) (filter (lambda (x) (raise x)) (list 5)))"), "5");
+}
+
+#[test]
+fn member_and_assoc_take_a_comparison() {
+    assert_eq!(value("(member (list 2) (list 1 (list 2) 3))"), "((2) 3)");
+    assert_eq!(value("(member 2 (list 1 3 5) (lambda (a b) (< a b)))"), "(3 5)");
+    assert_eq!(value("(assoc 2 (list (list 1 'a) (list 2 'b)))"), "(2 b)");
+}
+
+#[test]
+fn iota_counts_from_start_by_step() {
+    assert_eq!(value("(iota 3)"), "(0 1 2)");
+    assert_eq!(value("(iota 5 10 -3)"), "(10 7 4 1 -2)");
+    assert_eq!(value("(iota 3 -9223372036854775807 9223372036854775807)"),
+               "(-9223372036854775807 0 9223372036854775807)");
+}
+
+#[test]
+fn iota_past_the_integer_range_is_an_error() {
+    assert_eq!(error("(iota 3 9223372036854775807)"), "integer overflow in iota");
+    assert_eq!(error("(iota 5 0 9223372036854775807)"), "integer overflow in iota");
+    assert_eq!(value("(guard (e (#t (error-object-message e))) (iota 2 9223372036854775807 1))"),
+               "\"integer overflow in iota\"");
+}
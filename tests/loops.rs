@@ -0,0 +1,46 @@
+extern crate lithp;
+
+mod common;
+
+use common::value;
+
+#[test]
+fn letrec_bindings_can_refer_to_each_other() {
+    let program = "
+        (letrec ((even? (lambda (n) (cond ((= n 0) #t) (#t (odd? (- n 1))))))
+                 (odd? (lambda (n) (cond ((= n 0) #f) (#t (even? (- n 1)))))))
+          (list (even? 10) (odd? 7)))";
+    assert_eq!(value(program), "(#t #t)");
+    assert_eq!(value("(letrec* ((a 1) (b (+ a 1))) b)"), "2");
+}
+
+#[test]
+fn named_let_loops() {
+    let program = "
+        (let loop ((i 0) (acc (list)))
+          (cond ((= i 5) acc)
+                (#t (loop (+ i 1) (cons i acc)))))";
+    assert_eq!(value(program), "(4 3 2 1 0)");
+}
+
+#[test]
+fn named_let_runs_in_constant_space() {
+    assert_eq!(value("(let loop ((i 0)) (cond ((= i 20000) i) (#t (loop (+ i 1)))))"), "20000");
+}
+
+#[test]
+fn do_rebinds_each_variable_to_its_step() {
+    assert_eq!(value("(do ((i 0 (+ i 1)) (acc (list) (cons i acc))) ((= i 5) acc))"), "(4 3 2 1 0)");
+    // A variable without a step keeps its value.
+    assert_eq!(value("(do ((i 0 (+ i 1)) (k 7)) ((= i 3) k))"), "7");
+}
+
+#[test]
+fn closures_made_in_a_do_loop_see_their_own_iteration() {
+    let program = "
+        (define thunks
+          (do ((i 0 (+ i 1)) (acc (list) (cons (lambda () i) acc)))
+              ((= i 3) acc)))
+        (map (lambda (thunk) (thunk)) thunks)";
+    assert_eq!(value(program), "(2 1 0)");
+}
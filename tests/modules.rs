@@ -0,0 +1,77 @@
+extern crate lithp;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use common::{lithp, lithp_in, scratch, value};
+
+const LIST: &str = "(define-module (utils list) (export my-reverse my-length)
+                      (define helper (lambda (xs acc) (cond ((null? xs) acc)
+                                                            (#t (helper (cdr xs) (cons (car xs) acc))))))
+                      (define my-reverse (lambda (xs) (helper xs (list))))
+                      (define my-length length))\n";
+
+fn write(path: &Path, source: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, source).unwrap();
+}
+
+// A module (shared) whose `where` says which directory it was found in.
+fn shared(directory: &Path, name: &str) {
+    write(&directory.join(name).join("shared.lithp"),
+          &format!("(define-module (shared) (export where) (define where '{}))\n", name));
+}
+
+#[test]
+fn modules_are_found_on_the_path_then_by_the_program_then_here() {
+    let directory = scratch("search-path");
+    for name in ["first", "program", "here"] {
+        shared(&directory, name);
+    }
+    let program = directory.join("program/main.lithp");
+    write(&program, "(import (shared)) (display where)\n");
+    let program = program.to_str().unwrap();
+    let here = directory.join("here");
+    let path = format!("--path={}", directory.join("first").display());
+    assert_eq!(lithp_in(&here, &[&path, program], "").0, "first");
+    assert_eq!(lithp_in(&here, &[program], "").0, "program");
+    fs::remove_file(directory.join("program/shared.lithp")).unwrap();
+    assert_eq!(lithp_in(&here, &[program], "").0, "here");
+    assert_eq!(lithp_in(&directory, &[program], "").0, "Evaluation Error: module not found (shared)\n");
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn modules_are_loaded_once() {
+    let directory = scratch("load-once");
+    write(&directory.join("noisy.lithp"), "(display 'loading) (define-module (noisy) (export x) (define x 1))\n");
+    let path = format!("--path={}", directory.display());
+    let (output, _) = lithp(&[&path], "(import (noisy)) (import (noisy)) (let () (import (noisy)) (display x))\n");
+    assert_eq!(output, "loading1");
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn import_sets_narrow_and_rename_exports() {
+    let with_list = |source: &str| value(&format!("{}{}", LIST, source));
+    assert_eq!(with_list("(import (only (utils list) my-reverse)) (my-reverse (list 1 2))"), "(2 1)");
+    assert_eq!(with_list("(import (only (utils list) my-reverse)) (guard (e (#t 'unbound)) my-length)"), "unbound");
+    assert_eq!(with_list("(import (except (utils list) my-reverse)) (guard (e (#t 'unbound)) my-reverse)"),
+               "unbound");
+    assert_eq!(with_list("(import (prefix (utils list) list:)) (list:my-length (list:my-reverse (list 1 2)))"), "2");
+    assert_eq!(with_list("(import (rename (prefix (only (utils list) my-reverse) l:) (l:my-reverse rev))) \
+                          (rev (list 1 2 3))"),
+               "(3 2 1)");
+    assert_eq!(with_list("(import (utils list)) (guard (e (#t 'private)) helper)"), "private");
+}
+
+#[test]
+fn modules_importing_each_other_are_an_error() {
+    let directory = scratch("circular");
+    write(&directory.join("a.lithp"), "(import (b)) (define-module (a) (export x) (define x 1))\n");
+    write(&directory.join("b.lithp"), "(import (a)) (define-module (b) (export y) (define y 2))\n");
+    let path = format!("--path={}", directory.display());
+    assert_eq!(lithp(&[&path], "(import (a))\n").0, "Evaluation Error: circular import (a)\n");
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,39 @@
+extern crate lithp;
+
+mod common;
+
+use common::{error, value};
+
+#[test]
+fn rest_parameters_collect_the_remaining_arguments() {
+    assert_eq!(value("((lambda (a b . rest) (list a b rest)) 1 2 3 4)"), "(1 2 (3 4))");
+    assert_eq!(value("((lambda (a #!rest rest) rest) 1)"), "()");
+    assert_eq!(value("((lambda args args) 1 2 3)"), "(1 2 3)");
+}
+
+#[test]
+fn optional_parameters_default_when_left_out() {
+    let f = "(define f (lambda (a #!optional b (c (+ a 10))) (list a b c)))";
+    assert_eq!(value(&format!("{} (f 1)", f)), "(1 #f 11)");
+    assert_eq!(value(&format!("{} (f 1 2 3)", f)), "(1 2 3)");
+}
+
+#[test]
+fn keyword_parameters_are_passed_by_name() {
+    let f = "(define f (lambda (a #!key (width 80) height) (list a width height)))";
+    assert_eq!(value(&format!("{} (f 1)", f)), "(1 80 #f)");
+    assert_eq!(value(&format!("{} (f 1 #:height 5 #:width 10)", f)), "(1 10 5)");
+    assert_eq!(error(&format!("{} (f 1 #:depth 2)", f)), "unknown keyword argument #:depth");
+}
+
+#[test]
+fn arity_is_checked() {
+    assert_eq!(error("((lambda (a b) a) 1)"), "invalid closure arity 1");
+    assert_eq!(error("((lambda (a) a) 1 2)"), "invalid closure arity 2");
+}
+
+#[test]
+fn apply_spreads_its_last_argument() {
+    assert_eq!(value("(apply + 1 2 (list 3 4))"), "10");
+    assert_eq!(value("(apply (lambda args args) (list))"), "()");
+}
@@ -0,0 +1,27 @@
+extern crate lithp;
+
+mod common;
+
+use common::value;
+
+#[test]
+fn type_predicates() {
+    assert_eq!(value("(list (integer? 1) (integer? \"1\") (string? \"a\") (symbol? 'a) (symbol? \"a\"))"),
+               "(#t #f #t #t #f)");
+    assert_eq!(value("(list (list? (list)) (list? 1) (boolean? #f) (boolean? 0))"), "(#t #f #t #f)");
+    assert_eq!(value("(list (procedure? car) (procedure? (lambda () 1)) (procedure? 'car))"), "(#t #t #f)");
+}
+
+#[test]
+fn procedure_arity_gives_required_and_most_arguments() {
+    assert_eq!(value("(procedure-arity (lambda (a b) a))"), "(2 2)");
+    assert_eq!(value("(procedure-arity (lambda (a #!optional b) a))"), "(1 2)");
+    assert_eq!(value("(procedure-arity (lambda (a . rest) a))"), "(1 #f)");
+}
+
+#[test]
+fn procedure_name_is_the_name_it_was_defined_with() {
+    assert_eq!(value("(define f (lambda () 1)) (procedure-name f)"), "f");
+    assert_eq!(value("(procedure-name car)"), "car");
+    assert_eq!(value("(procedure-name (lambda () 1))"), "#f");
+}
@@ -0,0 +1,19 @@
+extern crate lithp;
+
+mod common;
+
+use common::{lithp, value};
+
+#[test]
+fn the_prelude_is_in_the_global_scope() {
+    assert_eq!(value("(fold cons (list) (list 1 2 3))"), "(3 2 1)");
+    assert_eq!(value("(range 2 5)"), "(2 3 4)");
+    assert_eq!(value("(range 5 2)"), "()");
+    assert_eq!(value("(null? (list))"), "#t");
+}
+
+#[test]
+fn no_prelude_leaves_it_out() {
+    let (output, _) = lithp(&["--no-prelude"], "(range 0 2)");
+    assert_eq!(output, "Evaluation Error: unknown identifier range\n");
+}
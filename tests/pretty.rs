@@ -0,0 +1,59 @@
+extern crate lithp;
+
+mod common;
+
+use common::{error, eval, lithp, value};
+use lithp::eval::Interpreter;
+use lithp::pretty::{pretty, DEFAULT_WIDTH};
+
+// The datum written in `source` laid out in `width` columns.
+fn laid_out(source: &str, width: usize) -> String {
+    let datum = eval(&mut Interpreter::new(), &format!("'{}", source)).unwrap();
+    pretty(&datum, width)
+}
+
+#[test]
+fn what_fits_stays_on_one_line() {
+    assert_eq!(laid_out("(a (b c) \"d\")", 20), "(a (b c) \"d\")");
+    assert_eq!(laid_out("(define x 1)", 12), "(define x 1)");
+}
+
+#[test]
+fn bodies_of_special_forms_are_indented_by_two() {
+    assert_eq!(laid_out("(let ((a 1) (b 2)) (display a) (display b))", 20),
+               "(let ((a 1) (b 2))\n  (display a)\n  (display b))");
+    assert_eq!(laid_out("(define f (lambda (n) (+ n 1)))", 20), "(define f\n  (lambda (n)\n    (+ n 1)))");
+}
+
+#[test]
+fn other_arguments_line_up_under_the_first() {
+    assert_eq!(laid_out("(foo alpha beta gamma delta)", 16), "(foo alpha\n     beta\n     gamma\n     delta)");
+    assert_eq!(laid_out("(cond ((= n 0) 1) (else (* n 2)))", 22), "(cond ((= n 0) 1)\n      (else (* n 2)))");
+}
+
+#[test]
+fn data_is_filled_to_the_width() {
+    assert_eq!(laid_out("(1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20)", 20),
+               "(1 2 3 4 5 6 7 8 9\n 10 11 12 13 14 15\n 16 17 18 19 20)");
+    let numbers: Vec<String> = (0..40).map(|n| n.to_string()).collect();
+    let lines = laid_out(&format!("({})", numbers.join(" ")), DEFAULT_WIDTH);
+    assert_eq!(lines.lines().count(), 2);
+    assert!(lines.lines().all(|line| line.len() <= DEFAULT_WIDTH));
+}
+
+#[test]
+fn pp_prints_at_the_width_given() {
+    assert_eq!(value("(with-output-to-string (lambda () (pp '(define x (list 1 2 3)) 12)))"),
+               "\"(define x\\n  (list 1\\n        2\\n        3))\\n\"");
+    assert_eq!(value("(with-output-to-string (lambda () (pp '(a b))))"), "\"(a b)\\n\"");
+    assert_eq!(error("(pp 1 0)"), "pp width must be a positive integer");
+}
+
+#[test]
+fn the_repl_pretty_prints_results() {
+    let (output, _) = lithp(&["--repl"], "(iota 40)\n");
+    let numbers: Vec<String> = (0..40).map(|n| n.to_string()).collect();
+    let datum = eval(&mut Interpreter::new(), &format!("'({})", numbers.join(" "))).unwrap();
+    assert_eq!(output, format!("> {}\n> \n", pretty(&datum, DEFAULT_WIDTH)));
+    assert!(output.lines().count() > 2);
+}
@@ -0,0 +1,82 @@
+extern crate lithp;
+
+mod common;
+
+use std::fs;
+use common::{eval, lithp, scratch};
+use lithp::eval::Interpreter;
+use lithp::profile::{Procedure, Report};
+
+const FIB: &str = "(define fib (lambda (n) (cond ((< n 2) n) (#t (+ (fib (- n 1)) (fib (- n 2)))))))";
+
+fn profiled(source: &str) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.start_profiling();
+    eval(&mut interpreter, source).unwrap();
+    interpreter
+}
+
+fn calls(interpreter: &Interpreter, procedure: &Procedure) -> u64 {
+    let stats = interpreter.profiler().unwrap().stats();
+    stats.iter().find(|(other, _)| *other == procedure).map_or(0, |(_, stats)| stats.calls)
+}
+
+#[test]
+fn every_call_is_counted() {
+    let interpreter = profiled(&format!("{} (fib 15)", FIB));
+    assert_eq!(calls(&interpreter, &Procedure::Closure("fib".into())), 1973);
+    assert_eq!(calls(&interpreter, &Procedure::Builtin("<")), 1973);
+    assert_eq!(calls(&interpreter, &Procedure::Builtin("-")), 1972);
+    assert_eq!(calls(&interpreter, &Procedure::Builtin("+")), 986);
+    let stats = interpreter.profiler().unwrap().stats();
+    let (_, fib) = stats.iter().find(|(procedure, _)| **procedure == Procedure::Closure("fib".into())).unwrap();
+    assert_eq!(fib.allocations, 1973);
+    assert!(fib.exclusive <= fib.inclusive);
+}
+
+#[test]
+fn the_report_has_a_line_for_each_procedure() {
+    let interpreter = profiled(&format!("{} (fib 10)", FIB));
+    let report = Report(interpreter.profiler().unwrap()).to_string();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0].split_whitespace().collect::<Vec<_>>(),
+               ["procedure", "calls", "inclusive", "ms", "exclusive", "ms", "allocations"]);
+    let fib: Vec<&str> = lines.iter().find(|line| line.starts_with("fib ")).unwrap().split_whitespace().collect();
+    assert_eq!((fib[1], fib[4]), ("177", "177"));
+    assert!(lines.iter().any(|line| line.starts_with("+ (built in) ")));
+    assert_eq!(lines.len(), 5);
+}
+
+#[test]
+fn folded_stacks_are_one_a_line_with_microseconds() {
+    let interpreter = profiled(&format!("{} (fib 12)", FIB));
+    let folded = interpreter.profiler().unwrap().folded();
+    for line in folded.lines() {
+        let (stack, micros) = line.rsplit_once(' ').unwrap();
+        assert!(micros.parse::<u64>().unwrap() > 0, "{}", line);
+        assert!(stack.split(';').all(|name| ["fib", "<", "-", "+"].contains(&name)), "{}", line);
+        assert!(stack.starts_with("fib") || stack == "<", "{}", line);
+    }
+    assert!(folded.lines().any(|line| line.starts_with("fib;fib;fib ")));
+}
+
+#[test]
+fn folded_stacks_are_cut_off_256_calls_deep() {
+    let down = "(define down (lambda (n) (cond ((= n 0) 0) (#t (+ 1 (down (- n 1))))))) (down 2000)";
+    let interpreter = profiled(down);
+    let folded = interpreter.profiler().unwrap().folded();
+    let deepest = folded.lines().map(|line| line.split(';').count()).max().unwrap();
+    assert_eq!(deepest, 256);
+    assert_eq!(calls(&interpreter, &Procedure::Closure("down".into())), 2001);
+}
+
+#[test]
+fn profile_writes_the_folded_stacks_to_a_file() {
+    let directory = scratch("profile");
+    let path = directory.join("fib.folded");
+    let flag = format!("--profile={}", path.display());
+    assert_eq!(lithp(&[&flag], &format!("{}\n(display (fib 10))\n", FIB)).0, "55");
+    let folded = fs::read_to_string(&path).unwrap();
+    assert!(folded.lines().any(|line| line.starts_with("fib;fib ")));
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,92 @@
+extern crate lithp;
+
+mod common;
+
+use common::{error, eval, lithp, value};
+use lithp::eval::{Interpreter, Options};
+
+fn pure() -> Interpreter {
+    Interpreter::with_options(Options { pure: true, ..Options::default() })
+}
+
+fn pure_error(source: &str) -> String {
+    match eval(&mut pure(), source) {
+        Ok(value) => panic!("{:?} returned {} in pure mode", source, value),
+        Err(error) => error.to_string()
+    }
+}
+
+#[test]
+fn set_rebinds_the_nearest_binding_and_returns_nothing() {
+    assert_eq!(value("(define x 1) (let ((y 2)) (set! x (+ x y))) x"), "3");
+    assert_eq!(value("(define x 1) (set! x 2)"), "||");
+}
+
+#[test]
+fn set_of_an_unbound_name_is_a_located_error() {
+    assert_eq!(error("(set! x 1)"), "attempt to set! undefined value x");
+    let (output, _) = lithp(&[], "(define y 1)\n  (set! x 1)\n");
+    assert_eq!(output, "Evaluation Error: <stdin>:2:3: attempt to set! undefined value x\n");
+}
+
+#[test]
+fn pure_mode_rejects_set() {
+    assert_eq!(pure_error("(define x 1) (set! x 2)"), "set! is not allowed in pure mode x");
+    assert_eq!(pure_error("(define f (lambda (n) (set! n 1)))"), "set! is not allowed in pure mode n");
+}
+
+#[test]
+fn pure_mode_rejects_redefinition() {
+    assert_eq!(pure_error("(define x 1) (define x 2)"), "redefinition is not allowed in pure mode x");
+    assert_eq!(pure_error("(define f (lambda () (define a 1) (define a 2) a))"),
+               "redefinition is not allowed in pure mode a");
+    // The prelude's bindings are bound already.
+    assert_eq!(pure_error("(define range 1)"), "redefinition is not allowed in pure mode range");
+}
+
+#[test]
+fn pure_mode_allows_shadowing_in_new_scopes() {
+    let mut interpreter = pure();
+    let value = eval(&mut interpreter, "(define x 1) (define f (lambda (x) (define y x) y)) (let ((x 2)) (f x))");
+    assert_eq!(value.unwrap().to_string(), "2");
+}
+
+#[test]
+fn pure_mode_rejects_forms_before_running_them() {
+    let (output, _) = lithp(&["--pure"], "(let ((a (display \"ran\"))) (set! a 1))");
+    assert_eq!(output, "Evaluation Error: set! is not allowed in pure mode a\n");
+    let (output, success) = lithp(&["check", "--pure"], "(define x 1)\n(define x 2)\n");
+    assert_eq!(output, "Error: redefinition is not allowed in pure mode x\n");
+    assert!(!success);
+}
+
+const MODULE: &str = "(define-module (m) (export a b) (define a 1) (define b 2))\n";
+
+#[test]
+fn pure_mode_rejects_imports_that_rebind_names() {
+    assert_eq!(pure_error(&format!("{}(define a 5) (import (m))", MODULE)),
+               "redefinition is not allowed in pure mode a");
+    assert_eq!(pure_error(&format!("{}(import (m)) (define b 5)", MODULE)),
+               "redefinition is not allowed in pure mode b");
+    assert_eq!(pure_error(&format!("{}(let () (define c 5) (import (rename (m) (a c))) c)", MODULE)),
+               "redefinition is not allowed in pure mode c");
+    assert_eq!(eval(&mut pure(), &format!("{}(define a 5) (import (only (m) b)) (+ a b)", MODULE))
+                   .unwrap().to_string(), "7");
+    let (output, success) = lithp(&["check", "--pure"], &format!("{}(define a 5)\n(import (m))\n", MODULE));
+    assert_eq!(output, "Error: redefinition is not allowed in pure mode a\n");
+    assert!(!success);
+}
+
+#[test]
+fn pure_mode_rejects_rebinding_by_modules_loaded_from_files() {
+    let directory = std::env::temp_dir().join(format!("lithp-pure-{}", std::process::id()));
+    std::fs::create_dir_all(directory.join("utils")).unwrap();
+    std::fs::write(directory.join("utils/list.lithp"),
+                   "(define-module (utils list) (export my-rev) (define my-rev reverse))\n").unwrap();
+    let path = format!("--path={}", directory.display());
+    let (output, _) = lithp(&["--pure", &path], "(define my-rev 5)\n(import (utils list))\n(display my-rev)\n");
+    assert_eq!(output, "Evaluation Error: redefinition is not allowed in pure mode my-rev\n");
+    let (output, _) = lithp(&["--pure", &path], "(import (utils list))\n(display (my-rev (list 1 2)))\n");
+    assert_eq!(output, "(2 1)");
+    std::fs::remove_dir_all(directory).unwrap();
+}
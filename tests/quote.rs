@@ -0,0 +1,17 @@
+extern crate lithp;
+
+mod common;
+
+use common::{error, value};
+
+#[test]
+fn quote_returns_its_datum_unevaluated() {
+    assert_eq!(value("'(a (b c) 1)"), "(a (b c) 1)");
+    assert_eq!(value("(quote (+ 1 2))"), "(+ 1 2)");
+    assert_eq!(value("''a"), "'a");
+}
+
+#[test]
+fn quote_takes_exactly_one_datum() {
+    assert_eq!(error("(quote a b)"), "quote takes exactly one datum");
+}
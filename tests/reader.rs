@@ -0,0 +1,57 @@
+extern crate lithp;
+
+use std::io::{BufRead, Cursor, Read};
+use lithp::reader::Reader;
+
+// Input that fails the test if read from past `limit` bytes, to show the
+// reader takes no more than it needs.
+struct Limited {
+    input: Cursor<Vec<u8>>,
+    limit: u64
+}
+
+impl Read for Limited {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl BufRead for Limited {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        assert!(self.input.position() < self.limit, "read past {} bytes", self.limit);
+        self.input.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.input.consume(amount)
+    }
+}
+
+fn read_all(input: &str) -> Vec<String> {
+    let mut reader = Reader::new(input.as_bytes());
+    let mut data = vec![];
+    loop {
+        match reader.read() {
+            Ok(Some(datum)) => data.push(datum.to_string()),
+            Ok(None) => return data,
+            Err(diagnostic) => data.push(format!("error {}", diagnostic))
+        }
+    }
+}
+
+#[test]
+fn data_are_read_one_at_a_time() {
+    assert_eq!(read_all("1 (a\n b) 'c\n\"two\nlines\""), ["1", "(a b)", "'c", "\"two\\nlines\""]);
+}
+
+#[test]
+fn only_the_lines_needed_are_read() {
+    let input = "(first)\n(second\n".to_string() + &"x".repeat(10_000);
+    let mut reader = Reader::new(Limited { input: Cursor::new(input.into_bytes()), limit: 8 });
+    assert_eq!(reader.read().unwrap().unwrap().to_string(), "(first)");
+}
+
+#[test]
+fn reading_carries_on_at_the_line_after_an_error() {
+    assert_eq!(read_all("(a)) (b)\n(c) (d"), ["(a)", "error 1:4: unexpected )", "(c)", "error 2:5: unexpected end of input"]);
+}
@@ -0,0 +1,29 @@
+extern crate lithp;
+
+mod common;
+
+use common::{error, value};
+
+#[test]
+fn written_values_read_back_equal() {
+    for datum in &["(1 -2 \"a \\\"b\\\"\\n\" |two words| sym)", "|12|", "(a '(b))", "\"\""] {
+        let program = format!("(equal? (read-string (with-output-to-string (lambda () (write '{})))) '{})", datum, datum);
+        assert_eq!(value(&program), "#t", "{}", datum);
+    }
+}
+
+#[test]
+fn display_writes_for_people() {
+    assert_eq!(value("(with-output-to-string (lambda () (display (list \"a b\" '|c d|))))"), "\"(a b c d)\"");
+}
+
+#[test]
+fn unreadable_objects_are_syntax_errors() {
+    assert_eq!(error("(read-string \"#<procedure car>\")"), "unreadable object");
+}
+
+#[test]
+fn read_string_stops_at_the_first_datum() {
+    assert_eq!(value("(read-string \"(1 2) 3\")"), "(1 2)");
+    assert_eq!(value("(eof-object? (read-string \"  \"))"), "#t");
+}
@@ -0,0 +1,66 @@
+extern crate lithp;
+
+mod common;
+
+use common::lithp;
+use lithp::parser::{parse_program, DEFAULT_MAX_DEPTH};
+
+// Every problem found in `source`, with where it was found.
+fn diagnostics(source: &str, max_depth: usize) -> Vec<String> {
+    parse_program(source, max_depth).diagnostics.iter().map(ToString::to_string).collect()
+}
+
+// What was read of `source`, problems and all.
+fn program(source: &str, max_depth: usize) -> String {
+    parse_program(source, max_depth).program.to_string()
+}
+
+#[test]
+fn every_error_is_reported_in_one_pass() {
+    assert_eq!(diagnostics("(a b))\n(c ')\n(d", DEFAULT_MAX_DEPTH),
+               ["1:6: unexpected )", "2:4: quote must be followed by a datum", "3:1: unmatched ("]);
+}
+
+#[test]
+fn what_was_read_is_still_usable() {
+    assert_eq!(program("(a b)) (c ') (d '(e", DEFAULT_MAX_DEPTH), "((a b) (c) (d '(e)))");
+    assert_eq!(program("x '", DEFAULT_MAX_DEPTH), "(x)");
+    assert_eq!(diagnostics("x '", DEFAULT_MAX_DEPTH), ["1:3: quote at end of input"]);
+}
+
+#[test]
+fn tokens_that_dont_scan_are_reported_too() {
+    assert_eq!(diagnostics("(a \"b)\n)", DEFAULT_MAX_DEPTH).len(), 2);
+}
+
+#[test]
+fn check_reports_every_error_without_running_anything() {
+    let (output, success) = lithp(&["check"], "(display 1)\n(a))\n'");
+    assert!(!success);
+    assert_eq!(output, "Syntax Error: 2:4: unexpected )\nSyntax Error: 3:1: quote at end of input\n");
+    assert_eq!(lithp(&["check"], "(display 1)"), (String::new(), true));
+}
+
+#[test]
+fn lists_nested_too_deeply_are_left_out() {
+    assert_eq!(diagnostics("(a (b (c (d)) e) f) g", 2), ["1:7: maximum nesting depth exceeded"]);
+    assert_eq!(program("(a (b (c (d)) e) f) g", 2), "((a (b e) f) g)");
+    assert_eq!(diagnostics("'''x", 2), ["1:3: maximum nesting depth exceeded"]);
+}
+
+#[test]
+fn deep_nesting_is_an_error_rather_than_a_crash() {
+    let deep = format!("{}{}", "(".repeat(200_000), ")".repeat(200_000));
+    let expected = "Syntax Error: 1:1001: maximum nesting depth exceeded\n";
+    assert_eq!(lithp(&["check"], &deep), (expected.to_string(), false));
+    assert_eq!(lithp(&["fmt"], &deep), ("Syntax Error: maximum nesting depth exceeded\n".to_string(), false));
+    let (output, _) = lithp(&["--no-prelude"], &format!("(read-string \"{}\")", deep));
+    assert_eq!(output, "Evaluation Error: maximum nesting depth exceeded\n");
+}
+
+#[test]
+fn the_depth_limit_can_be_changed() {
+    assert_eq!(lithp(&["fmt", "--max-depth=2"], "(a (b))\n"), ("(a (b))\n".to_string(), true));
+    assert!(!lithp(&["fmt", "--max-depth=2"], "(a (b '(c)))\n").1);
+    assert!(!lithp(&["check", "--max-depth=2"], "(a (b '(c)))\n").1);
+}
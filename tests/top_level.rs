@@ -0,0 +1,32 @@
+extern crate lithp;
+
+mod common;
+
+use common::{eval, lithp, value};
+use lithp::eval::Interpreter;
+
+#[test]
+fn definitions_last_between_forms() {
+    let mut interpreter = Interpreter::new();
+    eval(&mut interpreter, "(define x 1)").unwrap();
+    eval(&mut interpreter, "(define x (+ x 1))").unwrap();
+    assert_eq!(eval(&mut interpreter, "x").unwrap().to_string(), "2");
+}
+
+#[test]
+fn top_level_definitions_can_refer_forward() {
+    assert_eq!(value("(define f (lambda () (g))) (define g (lambda () 2)) (f)"), "2");
+}
+
+#[test]
+fn an_error_keeps_the_definitions_before_it() {
+    let mut interpreter = Interpreter::new();
+    assert!(eval(&mut interpreter, "(define x 1) (car) (define x 2)").is_err());
+    assert_eq!(eval(&mut interpreter, "x").unwrap().to_string(), "1");
+}
+
+#[test]
+fn forms_before_a_syntax_error_run() {
+    let (output, _) = lithp(&[], "(display 1)\n(define f (lambda () 2))\n(display (f))\n)");
+    assert_eq!(output, "12Syntax Error: 4:1: unexpected )\n");
+}